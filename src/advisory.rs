@@ -0,0 +1,285 @@
+use crate::dep::ResolvedDep;
+use crate::error::{CliError, CliResult};
+use crate::graph::DepGraph;
+use semver::VersionReq;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use toml::Value;
+
+/// A security advisory of the RustSec advisory database.
+#[derive(Clone, Debug)]
+pub struct Advisory {
+    pub id: String,
+    pub package: String,
+    pub title: Option<String>,
+    /// The kind of informational advisory. Only `unsound` ones are loaded.
+    pub informational: Option<String>,
+    pub patched: Vec<VersionReq>,
+    pub unaffected: Vec<VersionReq>,
+}
+
+impl Advisory {
+    /// Parses an advisory file. Current advisories are Markdown files with a TOML front matter in a
+    /// ```` ```toml ```` block, while older ones are plain TOML files. Returns `None` for
+    /// withdrawn advisories and informational ones other than `unsound`, such as unmaintained
+    /// crates, which don't describe vulnerabilities.
+    fn from_file(path: &Path) -> CliResult<Option<Self>> {
+        let content = fs::read_to_string(path)?;
+        let is_markdown = path.extension().is_some_and(|ext| ext == "md");
+
+        let (front_matter, body) = if is_markdown {
+            let start = content
+                .find("```toml")
+                .map(|i| i + "```toml".len())
+                .ok_or_else(|| invalid(path, "missing TOML front matter"))?;
+            let len = content[start..]
+                .find("```")
+                .ok_or_else(|| invalid(path, "unterminated TOML front matter"))?;
+            (&content[start..start + len], &content[start + len + 3..])
+        } else {
+            (&*content, "")
+        };
+        let toml: Value = toml::from_str(front_matter)
+            .map_err(|e| invalid(path, &format!("could not parse TOML: {}", e)))?;
+
+        let advisory = toml
+            .get("advisory")
+            .ok_or_else(|| invalid(path, "missing [advisory] table"))?;
+        let field = |name: &str| advisory.get(name).and_then(Value::as_str);
+        let informational = field("informational");
+        if advisory.get("withdrawn").is_some() || informational.is_some_and(|i| i != "unsound") {
+            return Ok(None);
+        }
+
+        let id = field("id").ok_or_else(|| invalid(path, "missing advisory id"))?;
+        let package = field("package").ok_or_else(|| invalid(path, "missing package name"))?;
+        let title = if is_markdown {
+            body.lines()
+                .find_map(|line| line.strip_prefix("# "))
+                .map(|title| title.trim().to_owned())
+        } else {
+            field("title").map(|title| title.to_owned())
+        };
+
+        // The version requirements are in a [versions] table in current advisories, and in the
+        // [advisory] table in older ones.
+        let (patched, unaffected) = match toml.get("versions") {
+            Some(versions) => (versions.get("patched"), versions.get("unaffected")),
+            None => (
+                advisory.get("patched_versions"),
+                advisory.get("unaffected_versions"),
+            ),
+        };
+
+        Ok(Some(Advisory {
+            id: id.into(),
+            package: package.into(),
+            title,
+            informational: informational.map(|i| i.to_owned()),
+            patched: version_reqs(path, patched)?,
+            unaffected: version_reqs(path, unaffected)?,
+        }))
+    }
+
+    /// Whether `dep` is affected, i.e. its version is neither patched nor unaffected.
+    pub fn affects(&self, dep: &ResolvedDep) -> bool {
+        if self.package != dep.name {
+            return false;
+        }
+        match &dep.version {
+            Some(ver) => !self
+                .patched
+                .iter()
+                .chain(self.unaffected.iter())
+                .any(|req| req.matches(ver)),
+            None => false,
+        }
+    }
+}
+
+fn invalid(path: &Path, reason: &str) -> CliError {
+    CliError::Generic(format!("Invalid advisory {}: {}", path.display(), reason))
+}
+
+fn version_reqs(path: &Path, value: Option<&Value>) -> CliResult<Vec<VersionReq>> {
+    let values = match value {
+        None => return Ok(vec![]),
+        Some(Value::Array(values)) => values,
+        Some(_) => return Err(invalid(path, "version requirements must be an array")),
+    };
+
+    values
+        .iter()
+        .map(|v| {
+            let req = v
+                .as_str()
+                .ok_or_else(|| invalid(path, "version requirements must be strings"))?;
+            VersionReq::parse(req).map_err(|e| {
+                invalid(
+                    path,
+                    &format!("invalid version requirement `{}`: {}", req, e),
+                )
+            })
+        })
+        .collect()
+}
+
+/// The advisories of a local checkout of the RustSec advisory database, indexed by package name.
+#[derive(Clone, Debug, Default)]
+pub struct AdvisoryDb {
+    advisories: HashMap<String, Vec<Advisory>>,
+}
+
+impl AdvisoryDb {
+    /// Loads the advisories from the `crates` directory of the database, where each crate has a
+    /// directory of advisory files. Files that can't be parsed, e.g. because they use a newer
+    /// format, are skipped with a warning.
+    pub fn load(path: &Path) -> CliResult<Self> {
+        let crates_dir = path.join("crates");
+        let crates_dir = if crates_dir.is_dir() {
+            crates_dir
+        } else {
+            path.to_path_buf()
+        };
+
+        let mut db = AdvisoryDb::default();
+        for crate_dir in fs::read_dir(&crates_dir)? {
+            let crate_dir = crate_dir?.path();
+            if !crate_dir.is_dir() {
+                continue;
+            }
+
+            for file in fs::read_dir(&crate_dir)? {
+                let file = file?.path();
+                let is_advisory = file
+                    .extension()
+                    .is_some_and(|ext| ext == "md" || ext == "toml");
+                if !is_advisory {
+                    continue;
+                }
+
+                match Advisory::from_file(&file) {
+                    Ok(Some(advisory)) => db
+                        .advisories
+                        .entry(advisory.package.clone())
+                        .or_default()
+                        .push(advisory),
+                    Ok(None) => (),
+                    Err(e) => eprintln!("warning: {} (skipped)", e),
+                }
+            }
+        }
+
+        for advisories in db.advisories.values_mut() {
+            advisories.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+
+        Ok(db)
+    }
+
+    /// Returns the advisories affecting `dep`.
+    pub fn find(&self, dep: &ResolvedDep) -> Vec<&Advisory> {
+        self.advisories.get(&dep.name).map_or(vec![], |advisories| {
+            advisories.iter().filter(|a| a.affects(dep)).collect()
+        })
+    }
+}
+
+/// Writes every vulnerable crate of the graph with its advisories and the path from the root that
+/// pulls it in. Returns the number of vulnerable crates.
+pub fn check<W: Write>(dg: &DepGraph, output: &mut W, db: &AdvisoryDb) -> CliResult<usize> {
+    let mut vulnerable = 0;
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        let advisories = db.find(dep);
+        if advisories.is_empty() {
+            continue;
+        }
+        vulnerable += 1;
+
+        writeln!(output, "{} v{}:", dep.name, dep.ver)?;
+        for advisory in advisories {
+            write!(output, "    {}", advisory.id)?;
+            if let Some(informational) = &advisory.informational {
+                write!(output, " ({})", informational)?;
+            }
+            if let Some(title) = &advisory.title {
+                write!(output, ": {}", title)?;
+            }
+            if advisory.patched.is_empty() {
+                writeln!(output, " (no patched versions)")?;
+            } else {
+                let patched = advisory
+                    .patched
+                    .iter()
+                    .map(|req| req.to_string())
+                    .collect::<Vec<_>>();
+                writeln!(output, " (patched: {})", patched.join(" or "))?;
+            }
+        }
+        dg.write_path_to(output, i)?;
+    }
+
+    Ok(vulnerable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        fs::write(dir.join(name), content).unwrap();
+    }
+
+    #[test]
+    fn load_skips_invalid_and_non_security_advisories() {
+        let dir = env::temp_dir().join(format!("cargo-deps-advisory-db-{}", std::process::id()));
+        let crate_dir = dir.join("crates").join("a");
+        fs::create_dir_all(&crate_dir).unwrap();
+
+        write(
+            &crate_dir,
+            "RUSTSEC-0000-0001.md",
+            "```toml\n[advisory]\nid = \"RUSTSEC-0000-0001\"\npackage = \"a\"\n\n\
+             [versions]\npatched = [\">= 1.2.0\"]\n```\n\n# Title\n",
+        );
+        write(
+            &crate_dir,
+            "RUSTSEC-0000-0002.md",
+            "```toml\n[advisory]\nid = \"RUSTSEC-0000-0002\"\npackage = \"a\"\n\
+             informational = \"unsound\"\n```\n",
+        );
+        write(
+            &crate_dir,
+            "RUSTSEC-0000-0003.md",
+            "```toml\n[advisory]\nid = \"RUSTSEC-0000-0003\"\npackage = \"a\"\n\
+             informational = \"unmaintained\"\n```\n",
+        );
+        write(
+            &crate_dir,
+            "RUSTSEC-0000-0004.toml",
+            "[advisory]\nid = \"RUSTSEC-0000-0004\"\npackage = \"a\"\n\
+             withdrawn = \"2020-01-01\"\n",
+        );
+        write(&crate_dir, "RUSTSEC-0000-0005.md", "# No front matter\n");
+        write(&crate_dir, "RUSTSEC-0000-0006.toml", "[advisory\n");
+
+        let db = AdvisoryDb::load(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let old = ResolvedDep::new("a".into(), "1.0.0".into());
+        let ids = db.find(&old).iter().map(|a| &*a.id).collect::<Vec<_>>();
+        assert_eq!(ids, ["RUSTSEC-0000-0001", "RUSTSEC-0000-0002"]);
+        assert_eq!(db.advisories["a"][0].title.as_deref(), Some("Title"));
+        assert_eq!(
+            db.advisories["a"][1].informational.as_deref(),
+            Some("unsound")
+        );
+
+        let patched = ResolvedDep::new("a".into(), "1.2.0".into());
+        let ids = db.find(&patched).iter().map(|a| &*a.id).collect::<Vec<_>>();
+        assert_eq!(ids, ["RUSTSEC-0000-0002"]);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::dep::ResolvedDep;
+use crate::error::{CliError, CliResult};
+use crate::graph::DepGraph;
+use semver::VersionReq;
+use std::io::Write;
+use toml::Value;
+
+/// A crate listed in the `[bans]` policy of the config file.
+#[derive(Clone, Debug)]
+pub struct BannedCrate {
+    pub name: String,
+    /// The versions that are banned. If `None`, all versions are.
+    pub req: Option<VersionReq>,
+    pub reason: Option<String>,
+}
+
+impl BannedCrate {
+    fn from_toml(value: &Value) -> CliResult<Self> {
+        let invalid = || {
+            CliError::Toml(
+                "`bans.deny` entries must be crate names or tables with a `name`, and optionally \
+                 a `version` requirement and a `reason`"
+                    .into(),
+            )
+        };
+
+        if let Some(name) = value.as_str() {
+            return Ok(BannedCrate {
+                name: name.into(),
+                req: None,
+                reason: None,
+            });
+        }
+
+        let name = value
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(invalid)?;
+        let req = match value.get("version") {
+            None => None,
+            Some(version) => {
+                let version = version.as_str().ok_or_else(invalid)?;
+                Some(VersionReq::parse(version).map_err(|e| {
+                    CliError::Toml(format!(
+                        "Invalid version requirement `{}` for banned crate `{}`: {}",
+                        version, name, e
+                    ))
+                })?)
+            }
+        };
+        let reason = match value.get("reason") {
+            None => None,
+            Some(reason) => Some(reason.as_str().ok_or_else(invalid)?.into()),
+        };
+
+        Ok(BannedCrate {
+            name: name.into(),
+            req,
+            reason,
+        })
+    }
+
+    fn matches(&self, dep: &ResolvedDep) -> bool {
+        if self.name != dep.name {
+            return false;
+        }
+        match &self.req {
+            None => true,
+            // A version that can't be parsed can't be matched against the requirement.
+            Some(req) => dep.version.as_ref().is_some_and(|ver| req.matches(ver)),
+        }
+    }
+}
+
+/// The `[bans]` policy of the config file.
+#[derive(Clone, Debug, Default)]
+pub struct BanPolicy {
+    pub deny: Vec<BannedCrate>,
+}
+
+impl BanPolicy {
+    pub fn from_toml(table: &Value) -> CliResult<Self> {
+        let deny = match table.get("deny") {
+            None => vec![],
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(BannedCrate::from_toml)
+                .collect::<CliResult<_>>()?,
+            Some(_) => return Err(CliError::Toml("`bans.deny` must be an array".into())),
+        };
+
+        Ok(BanPolicy { deny })
+    }
+
+    /// Returns the entry banning `dep`, if any.
+    pub fn find(&self, dep: &ResolvedDep) -> Option<&BannedCrate> {
+        self.deny.iter().find(|banned| banned.matches(dep))
+    }
+}
+
+/// Writes every banned crate of the graph along with the path from the root that pulls it in.
+/// Returns the number of banned crates.
+pub fn check<W: Write>(dg: &DepGraph, output: &mut W, policy: &BanPolicy) -> CliResult<usize> {
+    let mut banned = 0;
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        let entry = match policy.find(dep) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        banned += 1;
+
+        write!(output, "{} v{}: banned", dep.name, dep.ver)?;
+        if let Some(req) = &entry.req {
+            write!(output, " ({})", req)?;
+        }
+        match &entry.reason {
+            Some(reason) => writeln!(output, ": {}", reason)?,
+            None => writeln!(output)?,
+        }
+        dg.write_path_to(output, i)?;
+    }
+
+    Ok(banned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn policy(toml: &str) -> BanPolicy {
+        BanPolicy::from_toml(&toml.parse::<Value>().unwrap()).unwrap()
+    }
+
+    fn dep(name: &str, ver: &str) -> ResolvedDep {
+        ResolvedDep::new(name.into(), ver.into())
+    }
+
+    #[test]
+    fn bans_are_parsed_with_and_without_version_requirements() {
+        let names = policy("deny = [\"openssl\"]");
+        assert_eq!(names.deny.len(), 1);
+        assert_eq!(names.deny[0].name, "openssl");
+        assert!(names.deny[0].req.is_none() && names.deny[0].reason.is_none());
+
+        let tables = policy(
+            "deny = [{ name = \"openssl\" }, \
+             { name = \"time\", version = \"0.1\", reason = \"unsound\" }]",
+        );
+        assert_eq!(tables.deny.len(), 2);
+        assert_eq!(tables.deny[0].name, "openssl");
+        assert!(tables.deny[0].req.is_none() && tables.deny[0].reason.is_none());
+        assert_eq!(tables.deny[1].name, "time");
+        assert_eq!(tables.deny[1].req, Some(VersionReq::parse("0.1").unwrap()));
+        assert_eq!(tables.deny[1].reason.as_deref(), Some("unsound"));
+    }
+
+    #[test]
+    fn invalid_bans_are_rejected() {
+        for toml in &[
+            "deny = \"openssl\"",
+            "deny = [{ version = \"1\" }]",
+            "deny = [{ name = \"time\", version = \"not a version\" }]",
+            "deny = [{ name = \"time\", reason = 1 }]",
+        ] {
+            let table = toml.parse::<Value>().unwrap();
+            assert!(BanPolicy::from_toml(&table).is_err(), "{}", toml);
+        }
+    }
+
+    #[test]
+    fn version_requirements_select_the_banned_versions() {
+        let policy =
+            policy("deny = [{ name = \"time\", version = \"0.1\" }, { name = \"openssl\" }]");
+
+        assert!(policy.find(&dep("time", "0.1.44")).is_some());
+        assert!(policy.find(&dep("time", "0.2.0")).is_none());
+        assert!(policy.find(&dep("time", "0.2.7")).is_none());
+        assert!(policy.find(&dep("openssl", "0.10.30")).is_some());
+        assert!(policy.find(&dep("openssl-sys", "0.9.58")).is_none());
+    }
+
+    #[test]
+    fn check_writes_the_path_to_each_banned_crate() {
+        let mut dg = DepGraph::new(Config::from_args(&[]));
+        let root = dg.find_or_add("root", "1.0.0");
+        let chrono = dg.add_child(root, "chrono", "0.4.19");
+        dg.add_child(chrono, "time", "0.1.44");
+        dg.add_child(root, "time", "0.2.7");
+        let policy =
+            policy("deny = [{ name = \"time\", version = \"0.1\", reason = \"unsound\" }]");
+
+        let mut output = vec![];
+        assert_eq!(check(&dg, &mut output, &policy).unwrap(), 1);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "time v0.1.44: banned (^0.1): unsound\n    root -> chrono -> time\n"
+        );
+
+        let mut output = vec![];
+        assert_eq!(check(&dg, &mut output, &BanPolicy::default()).unwrap(), 0);
+        assert!(output.is_empty());
+    }
+}
@@ -1,15 +1,99 @@
-use crate::error::CliResult;
+use crate::advisory::AdvisoryDb;
+use crate::bans::BanPolicy;
+use crate::error::{CliError, CliResult};
+use crate::license::LicensePolicy;
+use crate::theme::{Attrs, Theme};
+use crate::util;
 use clap::ArgMatches;
+use std::path::Path;
+use toml::Value;
+
+/// The output formats the graph can be rendered to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Dot,
+    Svg,
+    Html,
+    GraphMl,
+    Gexf,
+    PlantUml,
+    CycloneDxJson,
+    CycloneDxXml,
+    Spdx,
+    SpdxJson,
+    Licenses,
+    Outdated,
+}
+
+impl Format {
+    pub const NAMES: &'static [&'static str] = &[
+        "dot",
+        "svg",
+        "html",
+        "graphml",
+        "gexf",
+        "plantuml",
+        "cyclonedx-json",
+        "cyclonedx-xml",
+        "spdx",
+        "spdx-json",
+        "licenses",
+        "outdated",
+    ];
+
+    fn from_name(name: &str) -> Self {
+        match name {
+            "svg" => Format::Svg,
+            "html" => Format::Html,
+            "graphml" => Format::GraphMl,
+            "gexf" => Format::Gexf,
+            "plantuml" => Format::PlantUml,
+            "cyclonedx-json" => Format::CycloneDxJson,
+            "cyclonedx-xml" => Format::CycloneDxXml,
+            "spdx" => Format::Spdx,
+            "spdx-json" => Format::SpdxJson,
+            "licenses" => Format::Licenses,
+            "outdated" => Format::Outdated,
+            _ => Format::Dot,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Config {
+    /// The advisory database. Vulnerable crates are highlighted in the graph.
+    pub advisory_db: Option<AdvisoryDb>,
+    pub audit: bool,
+    /// The `[bans]` policy of the config file. Banned crates are highlighted in the graph.
+    pub ban_policy: Option<BanPolicy>,
+    pub check_bans: bool,
+    pub color_by_license: bool,
+    pub dim_others: bool,
     pub dot_file: Option<String>,
+    /// The Graphviz layout engine.
+    pub engine: Option<String>,
     pub filter: Option<Vec<String>>,
+    pub format: Format,
+    pub highlight: Option<Vec<String>>,
+    pub host_target: bool,
     pub include_orphans: bool,
     pub include_vers: bool,
+    pub index_path: Option<String>,
+    pub keep_root_edges: bool,
+    pub legend: bool,
+    /// The policy to check the licenses against, instead of rendering the graph.
+    pub license_policy: Option<LicensePolicy>,
     pub manifest_path: String,
+    pub rank_by_depth: bool,
+    pub rank_labels: bool,
+    pub shade_outdated: bool,
+    pub sort: bool,
+    pub stable_ids: bool,
     pub subgraph: Option<Vec<String>>,
     pub subgraph_name: Option<String>,
+    pub theme: Theme,
+    pub transitive_reduction: bool,
+    pub vendor_dir: Option<String>,
 
     pub regular_deps: bool,
     pub build_deps: bool,
@@ -20,19 +104,79 @@ pub struct Config {
 impl Config {
     pub fn from_matches(m: &ArgMatches) -> CliResult<Self> {
         let all_deps = m.is_present("all-deps");
+        let config_file = match m.value_of("config") {
+            Some(path) => util::toml_from_file(path)?,
+            None => Value::Table(Default::default()),
+        };
+
+        let license_policy = if m.is_present("check-licenses") {
+            match config_file.get("licenses") {
+                Some(table) => Some(LicensePolicy::from_toml(table)?),
+                None => {
+                    return Err(CliError::Generic(
+                        "--check-licenses requires a [licenses] table in the config file".into(),
+                    ))
+                }
+            }
+        } else {
+            None
+        };
+
+        let check_bans = m.is_present("check-bans");
+        let ban_policy = match config_file.get("bans") {
+            Some(table) => Some(BanPolicy::from_toml(table)?),
+            None if check_bans => {
+                return Err(CliError::Generic(
+                    "--check-bans requires a [bans] table in the config file".into(),
+                ))
+            }
+            None => None,
+        };
+
+        let advisory_db = match m.value_of("advisory-db") {
+            Some(path) => Some(AdvisoryDb::load(Path::new(path))?),
+            None => None,
+        };
+
+        let mut theme = Theme::from_config(m.value_of("theme"), config_file.get("theme"))?;
+        set_graph_attrs(&mut theme, m, &config_file)?;
 
         Ok(Config {
+            advisory_db,
+            audit: m.is_present("audit"),
+            ban_policy,
+            check_bans,
+            color_by_license: m.is_present("color-by-license"),
+            dim_others: m.is_present("dim-others"),
             dot_file: m.value_of("dot-file").map(|s| s.into()),
+            engine: m.value_of("engine").map(|s| s.into()),
             filter: m
                 .values_of("filter")
                 .map(|deps| deps.map(|dep| dep.into()).collect()),
+            format: Format::from_name(m.value_of("format").unwrap_or("dot")),
+            highlight: m
+                .values_of("highlight")
+                .map(|patterns| patterns.map(|p| p.into()).collect()),
+            host_target: m.is_present("host-target"),
             include_orphans: m.is_present("include-orphans"),
             include_vers: m.is_present("include-versions"),
+            index_path: m.value_of("index").map(|s| s.into()),
+            keep_root_edges: m.is_present("keep-root-edges"),
+            legend: m.is_present("legend"),
+            license_policy,
             manifest_path: m.value_of("manifest-path").unwrap_or("Cargo.toml").into(),
+            rank_by_depth: m.is_present("rank-by-depth"),
+            rank_labels: m.is_present("rank-labels"),
+            shade_outdated: m.is_present("shade-outdated"),
+            sort: m.is_present("sort"),
+            stable_ids: m.is_present("stable-ids"),
             subgraph: m
                 .values_of("subgraph")
                 .map(|deps| deps.map(|dep| dep.into()).collect()),
             subgraph_name: m.value_of("subgraph-name").map(|s| s.into()),
+            theme,
+            transitive_reduction: m.is_present("transitive-reduction"),
+            vendor_dir: m.value_of("vendor-dir").map(|s| s.into()),
 
             regular_deps: !m.is_present("no-regular-deps"),
             build_deps: all_deps || m.is_present("build-deps"),
@@ -41,3 +185,65 @@ impl Config {
         })
     }
 }
+
+impl Config {
+    /// Returns true if a check is run instead of rendering the graph.
+    pub fn is_check(&self) -> bool {
+        self.license_policy.is_some() || self.check_bans || self.audit
+    }
+}
+
+#[cfg(test)]
+impl Config {
+    /// The config of `cargo deps <args>`.
+    pub fn from_args(args: &[&str]) -> Self {
+        let m = crate::cli().get_matches_from(["cargo", "deps"].iter().chain(args));
+        Config::from_matches(m.subcommand_matches("deps").unwrap()).unwrap()
+    }
+}
+
+/// Sets the default graph, node and edge attributes of the DOT output, over those of the theme:
+/// first from the `[graph]`, `[node]` and `[edge]` tables of the config file, then from the
+/// options for common attributes, and last from the `--*-attr KEY=VALUE` options.
+fn set_graph_attrs(theme: &mut Theme, m: &ArgMatches, config_file: &Value) -> CliResult<()> {
+    for (name, attrs) in [
+        ("graph", &mut theme.graph),
+        ("node", &mut theme.node),
+        ("edge", &mut theme.edge),
+    ] {
+        if let Some(value) = config_file.get(name) {
+            attrs.merge(&Attrs::from_toml(value, name)?);
+        }
+    }
+
+    if let Some(engine) = m.value_of("engine") {
+        theme.graph.set("layout", engine);
+    }
+    for name in &["rankdir", "ranksep", "splines"] {
+        if let Some(value) = m.value_of(name) {
+            theme.graph.set(name, value);
+        }
+    }
+    if m.is_present("concentrate") {
+        theme.graph.set("concentrate", "true");
+    }
+    if let Some(font) = m.value_of("font") {
+        for attrs in [&mut theme.graph, &mut theme.node, &mut theme.edge] {
+            attrs.set("fontname", font);
+        }
+    }
+
+    for (name, attrs) in [
+        ("graph-attr", &mut theme.graph),
+        ("node-attr", &mut theme.node),
+        ("edge-attr", &mut theme.edge),
+    ] {
+        for attr in m.values_of(name).into_iter().flatten() {
+            // The values were validated to be of the form KEY=VALUE.
+            let (key, value) = attr.split_at(attr.find('=').unwrap());
+            attrs.set(key, &value[1..]);
+        }
+    }
+
+    Ok(())
+}
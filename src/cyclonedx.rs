@@ -0,0 +1,406 @@
+use crate::dep::ResolvedDep;
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use crate::util::{json_escape, xml_escape};
+use std::collections::HashMap;
+use std::io::Write;
+
+const SPEC_VERSION: &str = "1.5";
+
+/// Maps the kind flags of a dependency to a component scope. Dev dependencies are not part of the
+/// shipped software, so they are only excluded if they aren't needed any other way.
+fn scope(dep: &ResolvedDep) -> Option<&'static str> {
+    if dep.is_regular || dep.is_build {
+        Some("required")
+    } else if dep.is_optional {
+        Some("optional")
+    } else if dep.is_dev {
+        Some("excluded")
+    } else {
+        None
+    }
+}
+
+/// Returns the `bom-ref` of each node, indexed by node: its package URL, followed by its source
+/// if another node has the same package URL, e.g. a registry crate and its git fork.
+fn bom_refs(dg: &DepGraph) -> Vec<String> {
+    let purls = dg.nodes.iter().map(|dep| dep.purl()).collect::<Vec<_>>();
+    let mut counts = HashMap::new();
+    for purl in &purls {
+        *counts.entry(purl).or_insert(0) += 1;
+    }
+
+    purls
+        .iter()
+        .zip(&dg.nodes)
+        .map(|(purl, dep)| match counts[purl] {
+            1 => purl.clone(),
+            _ => format!("{} ({})", purl, dep.source.as_deref().unwrap_or("path")),
+        })
+        .collect()
+}
+
+/// Returns the references of the dependencies of each node, indexed by node.
+fn depends_on(dg: &DepGraph, bom_refs: &[String]) -> Vec<Vec<String>> {
+    dg.children()
+        .iter()
+        .map(|children| {
+            let mut refs = children
+                .iter()
+                .map(|&child| bom_refs[child].clone())
+                .collect::<Vec<_>>();
+            refs.sort();
+            refs.dedup();
+            refs
+        })
+        .collect()
+}
+
+/// Writes a CycloneDX software bill of materials in the JSON format.
+pub fn render_json<W: Write>(dg: &DepGraph, output: &mut W) -> CliResult<()> {
+    writeln!(output, "{{")?;
+    writeln!(output, "  \"bomFormat\": \"CycloneDX\",")?;
+    writeln!(output, "  \"specVersion\": \"{}\",", SPEC_VERSION)?;
+    writeln!(output, "  \"version\": 1,")?;
+    writeln!(output, "  \"metadata\": {{")?;
+    writeln!(output, "    \"tools\": {{")?;
+    writeln!(output, "      \"components\": [")?;
+    writeln!(
+        output,
+        "        {{ \"type\": \"application\", \"name\": \"cargo-deps\", \"version\": \"{}\" }}",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(output, "      ]")?;
+    writeln!(output, "    }},")?;
+    let bom_refs = bom_refs(dg);
+    write!(output, "    \"component\": ")?;
+    write_json_component(output, &dg.nodes[0], &bom_refs[0], "application", "    ")?;
+    writeln!(output)?;
+    writeln!(output, "  }},")?;
+
+    writeln!(output, "  \"components\": [")?;
+    for (i, dep) in dg.nodes.iter().enumerate().skip(1) {
+        write!(output, "    ")?;
+        write_json_component(output, dep, &bom_refs[i], "library", "    ")?;
+        writeln!(output, "{}", if i + 1 < dg.nodes.len() { "," } else { "" })?;
+    }
+    writeln!(output, "  ],")?;
+
+    writeln!(output, "  \"dependencies\": [")?;
+    let depends_on = depends_on(dg, &bom_refs);
+    for (i, bom_ref) in bom_refs.iter().enumerate() {
+        let refs = depends_on[i]
+            .iter()
+            .map(|r| format!("\"{}\"", json_escape(r)))
+            .collect::<Vec<_>>();
+        writeln!(
+            output,
+            "    {{ \"ref\": \"{}\", \"dependsOn\": [{}] }}{}",
+            json_escape(bom_ref),
+            refs.join(", "),
+            if i + 1 < dg.nodes.len() { "," } else { "" }
+        )?;
+    }
+    writeln!(output, "  ]")?;
+    writeln!(output, "}}")?;
+
+    Ok(())
+}
+
+fn write_json_component<W: Write>(
+    output: &mut W,
+    dep: &ResolvedDep,
+    bom_ref: &str,
+    ty: &str,
+    indent: &str,
+) -> CliResult<()> {
+    let purl = json_escape(&dep.purl());
+
+    writeln!(output, "{{")?;
+    writeln!(output, "{}  \"type\": \"{}\",", indent, ty)?;
+    writeln!(
+        output,
+        "{}  \"bom-ref\": \"{}\",",
+        indent,
+        json_escape(bom_ref)
+    )?;
+    writeln!(
+        output,
+        "{}  \"name\": \"{}\",",
+        indent,
+        json_escape(&dep.name)
+    )?;
+    writeln!(
+        output,
+        "{}  \"version\": \"{}\",",
+        indent,
+        json_escape(&dep.ver)
+    )?;
+    if ty == "library" {
+        if let Some(scope) = scope(dep) {
+            writeln!(output, "{}  \"scope\": \"{}\",", indent, scope)?;
+        }
+    }
+    if let Some(checksum) = &dep.checksum {
+        writeln!(
+            output,
+            "{}  \"hashes\": [{{ \"alg\": \"SHA-256\", \"content\": \"{}\" }}],",
+            indent,
+            json_escape(checksum)
+        )?;
+    }
+    if let Some(license) = dep.license_expr() {
+        writeln!(
+            output,
+            "{}  \"licenses\": [{{ \"expression\": \"{}\" }}],",
+            indent,
+            json_escape(&license.to_string())
+        )?;
+    }
+    writeln!(output, "{}  \"purl\": \"{}\"", indent, purl)?;
+    write!(output, "{}}}", indent)?;
+
+    Ok(())
+}
+
+/// Writes a CycloneDX software bill of materials in the XML format.
+pub fn render_xml<W: Write>(dg: &DepGraph, output: &mut W) -> CliResult<()> {
+    writeln!(output, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        output,
+        "<bom xmlns=\"http://cyclonedx.org/schema/bom/{}\" version=\"1\">",
+        SPEC_VERSION
+    )?;
+    writeln!(output, "\t<metadata>")?;
+    writeln!(output, "\t\t<tools>")?;
+    writeln!(output, "\t\t\t<components>")?;
+    writeln!(output, "\t\t\t\t<component type=\"application\">")?;
+    writeln!(output, "\t\t\t\t\t<name>cargo-deps</name>")?;
+    writeln!(
+        output,
+        "\t\t\t\t\t<version>{}</version>",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(output, "\t\t\t\t</component>")?;
+    writeln!(output, "\t\t\t</components>")?;
+    writeln!(output, "\t\t</tools>")?;
+    let bom_refs = bom_refs(dg);
+    write_xml_component(output, &dg.nodes[0], &bom_refs[0], "application", "\t\t")?;
+    writeln!(output, "\t</metadata>")?;
+
+    writeln!(output, "\t<components>")?;
+    for (i, dep) in dg.nodes.iter().enumerate().skip(1) {
+        write_xml_component(output, dep, &bom_refs[i], "library", "\t\t")?;
+    }
+    writeln!(output, "\t</components>")?;
+
+    writeln!(output, "\t<dependencies>")?;
+    let depends_on = depends_on(dg, &bom_refs);
+    for (i, bom_ref) in bom_refs.iter().enumerate() {
+        writeln!(output, "\t\t<dependency ref=\"{}\">", xml_escape(bom_ref))?;
+        for r in &depends_on[i] {
+            writeln!(output, "\t\t\t<dependency ref=\"{}\"/>", xml_escape(r))?;
+        }
+        writeln!(output, "\t\t</dependency>")?;
+    }
+    writeln!(output, "\t</dependencies>")?;
+    writeln!(output, "</bom>")?;
+
+    Ok(())
+}
+
+fn write_xml_component<W: Write>(
+    output: &mut W,
+    dep: &ResolvedDep,
+    bom_ref: &str,
+    ty: &str,
+    indent: &str,
+) -> CliResult<()> {
+    let purl = xml_escape(&dep.purl());
+
+    writeln!(
+        output,
+        "{}<component type=\"{}\" bom-ref=\"{}\">",
+        indent,
+        ty,
+        xml_escape(bom_ref)
+    )?;
+    writeln!(output, "{}\t<name>{}</name>", indent, xml_escape(&dep.name))?;
+    writeln!(
+        output,
+        "{}\t<version>{}</version>",
+        indent,
+        xml_escape(&dep.ver)
+    )?;
+    if ty == "library" {
+        if let Some(scope) = scope(dep) {
+            writeln!(output, "{}\t<scope>{}</scope>", indent, scope)?;
+        }
+    }
+    if let Some(checksum) = &dep.checksum {
+        writeln!(output, "{}\t<hashes>", indent)?;
+        writeln!(
+            output,
+            "{}\t\t<hash alg=\"SHA-256\">{}</hash>",
+            indent,
+            xml_escape(checksum)
+        )?;
+        writeln!(output, "{}\t</hashes>", indent)?;
+    }
+    if let Some(license) = dep.license_expr() {
+        writeln!(output, "{}\t<licenses>", indent)?;
+        writeln!(
+            output,
+            "{}\t\t<expression>{}</expression>",
+            indent,
+            xml_escape(&license.to_string())
+        )?;
+        writeln!(output, "{}\t</licenses>", indent)?;
+    }
+    writeln!(output, "{}\t<purl>{}</purl>", indent, purl)?;
+    writeln!(output, "{}</component>", indent)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dep::DepKind::{Build, Dev, Optional, Regular};
+    use crate::graph::Edge;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+
+    /// A root with a regular, a build, a dev and an optional dependency, and a git fork of the
+    /// regular dependency, with the same name and version, as a dependency of the build one.
+    fn graph() -> DepGraph {
+        let mut dg = DepGraph::new(Config::from_args(&[]));
+        let root = dg.find_or_add("root", "1.0.0");
+        let regular = dg.add_child(root, "rand", "0.6.5");
+        let build = dg.add_child(root, "cc", "1.0.0");
+        dg.add_child(root, "criterion", "0.3.0");
+        dg.add_child(root, "serde", "1.0.100");
+        dg.nodes[regular].source =
+            Some("registry+https://github.com/rust-lang/crates.io-index".into());
+        dg.nodes[regular].checksum = Some("ab12".into());
+        dg.nodes[regular].license = Some("MIT OR Apache-2.0".into());
+
+        let mut fork = ResolvedDep::new("rand".into(), "0.6.5".into());
+        fork.source = Some("git+https://example.com/\"fork\"".into());
+        dg.nodes.push(fork);
+        dg.edges.push(Edge(build, dg.nodes.len() - 1));
+
+        let mut declared = HashMap::new();
+        for (name, kind) in [
+            ("rand", Regular),
+            ("cc", Build),
+            ("criterion", Dev),
+            ("serde", Optional),
+        ] {
+            declared.insert(name.to_string(), vec![kind]);
+        }
+        dg.set_resolved_kind(&declared);
+        dg
+    }
+
+    const REGISTRY_RAND: &str =
+        "pkg:cargo/rand@0.6.5 (registry+https://github.com/rust-lang/crates.io-index)";
+    const FORK_RAND: &str = "pkg:cargo/rand@0.6.5 (git+https://example.com/\"fork\")";
+
+    #[test]
+    fn json_lists_components_with_scopes_and_hashes() {
+        let mut output = vec![];
+        render_json(&graph(), &mut output).unwrap();
+        let bom: Value = serde_json::from_slice(&output).unwrap();
+
+        assert_eq!(
+            bom["metadata"]["component"]["bom-ref"],
+            "pkg:cargo/root@1.0.0"
+        );
+        assert_eq!(bom["metadata"]["component"]["type"], "application");
+
+        let components = bom["components"].as_array().unwrap();
+        let summary = components
+            .iter()
+            .map(|c| (c["bom-ref"].as_str().unwrap(), c["scope"].as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (REGISTRY_RAND, Some("required")),
+                ("pkg:cargo/cc@1.0.0", Some("required")),
+                ("pkg:cargo/criterion@0.3.0", Some("excluded")),
+                ("pkg:cargo/serde@1.0.100", Some("optional")),
+                (FORK_RAND, Some("required")),
+            ]
+        );
+
+        let rand = &components[0];
+        assert_eq!(rand["purl"], "pkg:cargo/rand@0.6.5");
+        assert_eq!(
+            rand["hashes"],
+            json!([{ "alg": "SHA-256", "content": "ab12" }])
+        );
+        assert_eq!(
+            rand["licenses"],
+            json!([{ "expression": "MIT OR Apache-2.0" }])
+        );
+        assert!(components[1].get("hashes").is_none());
+
+        let dependencies = bom["dependencies"].as_array().unwrap();
+        assert_eq!(dependencies.len(), 6);
+        assert_eq!(
+            dependencies[0],
+            json!({
+                "ref": "pkg:cargo/root@1.0.0",
+                "dependsOn": [
+                    "pkg:cargo/cc@1.0.0",
+                    "pkg:cargo/criterion@0.3.0",
+                    REGISTRY_RAND,
+                    "pkg:cargo/serde@1.0.100",
+                ],
+            })
+        );
+        assert_eq!(
+            dependencies[2],
+            json!({ "ref": "pkg:cargo/cc@1.0.0", "dependsOn": [FORK_RAND] })
+        );
+    }
+
+    #[test]
+    fn xml_lists_components_with_scopes_and_hashes() {
+        let mut output = vec![];
+        render_xml(&graph(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let fork = "pkg:cargo/rand@0.6.5 (git+https://example.com/&quot;fork&quot;)";
+        for expected in [
+            "\t\t<component type=\"application\" bom-ref=\"pkg:cargo/root@1.0.0\">\n",
+            "\t\t\t<scope>required</scope>\n\t\t\t<hashes>\n\
+             \t\t\t\t<hash alg=\"SHA-256\">ab12</hash>\n\t\t\t</hashes>\n\
+             \t\t\t<licenses>\n\t\t\t\t<expression>MIT OR Apache-2.0</expression>\n",
+            "\t\t\t<name>criterion</name>\n\t\t\t<version>0.3.0</version>\n\
+             \t\t\t<scope>excluded</scope>\n",
+            "\t\t\t<name>serde</name>\n\t\t\t<version>1.0.100</version>\n\
+             \t\t\t<scope>optional</scope>\n",
+            &format!("\t\t<component type=\"library\" bom-ref=\"{}\">\n", fork),
+            &format!(
+                "\t\t<dependency ref=\"pkg:cargo/cc@1.0.0\">\n\
+                 \t\t\t<dependency ref=\"{}\"/>\n\t\t</dependency>\n",
+                fork
+            ),
+        ] {
+            assert!(
+                output.contains(expected),
+                "{}\nnot in:\n{}",
+                expected,
+                output
+            );
+        }
+        assert_eq!(output.matches("<component ").count(), 7);
+        assert_eq!(output.matches("</component>").count(), 7);
+        assert_eq!(output.matches("<dependency ref=").count(), 6 + 5);
+        assert!(output.ends_with("\t</dependencies>\n</bom>\n"));
+    }
+}
@@ -1,4 +1,10 @@
 use crate::config::Config;
+use crate::license::{LicenseExpr, LicenseFamily};
+use crate::manifest::PackageManifest;
+use crate::theme::Attrs;
+use crate::util::dot_escape;
+use semver::Version;
+use std::cmp::Ordering;
 use std::io::{Result, Write};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -10,6 +16,112 @@ pub enum DepKind {
     Unknown,
 }
 
+impl DepKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            DepKind::Regular => "regular",
+            DepKind::Build => "build",
+            DepKind::Dev => "dev",
+            DepKind::Optional => "optional",
+            DepKind::Unknown => "unknown",
+        }
+    }
+
+    /// The color used to draw dependencies of this kind, by name and as RGB components for the
+    /// formats that don't accept color names.
+    fn color_def(self) -> (&'static str, (u8, u8, u8)) {
+        match self {
+            DepKind::Regular => ("black", (0, 0, 0)),
+            DepKind::Build => ("purple", (128, 0, 128)),
+            DepKind::Dev => ("blue", (0, 0, 255)),
+            DepKind::Optional => ("red", (255, 0, 0)),
+            DepKind::Unknown => ("orange", (255, 165, 0)),
+        }
+    }
+
+    /// The name of the color used to draw dependencies of this kind.
+    pub fn color(self) -> &'static str {
+        self.color_def().0
+    }
+
+    /// The RGB components of `color()`.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        self.color_def().1
+    }
+}
+
+/// Highlights drawn on top of the theme, from the most to the least important. A node only gets
+/// the first that applies to it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Annotation {
+    Banned,
+    Vulnerable,
+    Highlighted,
+    CompatibleUpdate,
+    IncompatibleUpdate,
+}
+
+impl Annotation {
+    pub fn description(self) -> &'static str {
+        match self {
+            Annotation::Banned => "banned",
+            Annotation::Vulnerable => "vulnerable",
+            Annotation::Highlighted => "highlighted",
+            Annotation::CompatibleUpdate => "compatible update",
+            Annotation::IncompatibleUpdate => "incompatible update",
+        }
+    }
+
+    /// Fills the node with the color of this annotation.
+    pub fn apply(self, attrs: &mut Attrs) {
+        let (fillcolor, fontcolor) = match self {
+            Annotation::Banned => ("crimson", Some("white")),
+            Annotation::Vulnerable => ("orangered", Some("white")),
+            Annotation::Highlighted => ("gold", Some("black")),
+            Annotation::CompatibleUpdate => ("gray75", None),
+            Annotation::IncompatibleUpdate => ("gray90", None),
+        };
+
+        attrs.add_style("filled");
+        attrs.set("fillcolor", fillcolor);
+        if let Some(fontcolor) = fontcolor {
+            attrs.set("fontcolor", fontcolor);
+        }
+    }
+}
+
+/// Where a dependency is compiled to when cross-compiling.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Platform {
+    Host,
+    Target,
+    Both,
+    Unknown,
+}
+
+impl Platform {
+    pub fn description(self) -> &'static str {
+        match self {
+            Platform::Host => "host only",
+            Platform::Target => "target only",
+            Platform::Both => "host and target",
+            Platform::Unknown => "unknown platform",
+        }
+    }
+
+    /// The shape of the nodes of this platform with `--host-target`, which replaces the shape of
+    /// the theme. Deps of unknown platform can't be reached from the root with a known kind, such
+    /// as orphans kept by `--include-orphans`.
+    pub fn shape(self) -> &'static str {
+        match self {
+            Platform::Host => "hexagon",
+            Platform::Target => "ellipse",
+            Platform::Both => "octagon",
+            Platform::Unknown => "diamond",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct DeclaredDep {
     pub name: String,
@@ -26,32 +138,121 @@ impl DeclaredDep {
 pub struct ResolvedDep {
     pub name: String,
     pub ver: String,
+    // `ver` parsed as semver, if valid.
+    pub version: Option<Version>,
+    pub source: Option<String>,
+    pub checksum: Option<String>,
+    pub license: Option<String>,
+    pub license_file: Option<String>,
+    pub manifest: Option<PackageManifest>,
+    // Whether this is the root or another member of its workspace.
+    pub is_workspace_member: bool,
     pub force_write_ver: bool,
 
+    // The contexts this dependency is compiled in. `is_build` means that it is compiled for the
+    // host, as a build dependency, a procedural macro, or a dependency of either. The other flags
+    // mean that it is compiled for the target as part of the corresponding root dependency kind.
     pub is_regular: bool,
     pub is_build: bool,
     pub is_dev: bool,
     pub is_optional: bool,
+
+    // Annotations set by the policies of the config file and the advisory database.
+    pub is_banned: bool,
+    pub is_vulnerable: bool,
+
+    // Set by `--highlight`. Dimmed deps are neither highlighted nor next to a highlighted dep.
+    pub is_highlighted: bool,
+    pub is_dimmed: bool,
+
+    // The length of the shortest path from the root, taken before the transitive reduction, which
+    // can make paths longer.
+    pub depth: Option<usize>,
+
+    // The newest releases found in the crates.io index, if newer than this version.
+    pub newer_compatible: Option<Version>,
+    pub newer_incompatible: Option<Version>,
 }
 
 impl ResolvedDep {
     pub fn new(name: String, ver: String) -> Self {
         ResolvedDep {
             name,
+            version: Version::parse(&ver).ok(),
             ver,
+            source: None,
+            checksum: None,
+            license: None,
+            license_file: None,
+            manifest: None,
+            is_workspace_member: false,
             force_write_ver: false,
 
             is_regular: false,
             is_build: false,
             is_dev: false,
             is_optional: false,
+
+            is_banned: false,
+            is_vulnerable: false,
+
+            is_highlighted: false,
+            is_dimmed: false,
+
+            depth: None,
+
+            newer_compatible: None,
+            newer_incompatible: None,
+        }
+    }
+
+    /// Compares the versions of two dependencies by semver precedence, or as strings if either of
+    /// them isn't a valid semver version.
+    pub fn cmp_version(&self, other: &Self) -> Ordering {
+        match (&self.version, &other.version) {
+            (Some(a), Some(b)) => a.cmp(b),
+            _ => self.ver.cmp(&other.ver),
         }
     }
 
+    /// Returns true if a newer release of this dependency was found in the crates.io index.
+    pub fn is_outdated(&self) -> bool {
+        self.newer_compatible.is_some() || self.newer_incompatible.is_some()
+    }
+
+    /// Returns true if this is a procedural macro crate, which is always compiled for the host.
+    pub fn is_proc_macro(&self) -> bool {
+        self.manifest.as_ref().is_some_and(|m| m.is_proc_macro)
+    }
+
+    /// Returns true if `dep_name` is a regular dependency of this package, i.e. it is not declared
+    /// only as a build dependency.
+    pub fn has_regular_dep(&self, dep_name: &str) -> bool {
+        match &self.manifest {
+            None => true,
+            Some(m) => {
+                m.deps.iter().any(|dep| dep == dep_name)
+                    || !m.build_deps.iter().any(|dep| dep == dep_name)
+            }
+        }
+    }
+
+    /// Returns true if `dep_name` is declared as a build dependency of this package.
+    pub fn has_build_dep(&self, dep_name: &str) -> bool {
+        self.manifest
+            .as_ref()
+            .is_some_and(|m| m.build_deps.iter().any(|dep| dep == dep_name))
+    }
+
     pub fn kind(&self) -> DepKind {
+        // Procedural macros are always compiled for the host, so `is_build` only tells how they
+        // are pulled in if no other flag does.
+        let is_build = self.is_build
+            && !(self.is_proc_macro() && (self.is_regular || self.is_dev || self.is_optional));
+
         if self.is_regular {
             DepKind::Regular
-        } else if self.is_build {
+        } else if is_build {
             DepKind::Build
         } else if self.is_dev {
             DepKind::Dev
@@ -62,21 +263,119 @@ impl ResolvedDep {
         }
     }
 
-    pub fn label<W: Write>(&self, w: &mut W, cfg: &Config, i: usize) -> Result<()> {
-        let name = if self.force_write_ver || cfg.include_vers {
+    pub fn platform(&self) -> Platform {
+        let is_target =
+            (self.is_regular || self.is_dev || self.is_optional) && !self.is_proc_macro();
+
+        match (self.is_build, is_target) {
+            (true, true) => Platform::Both,
+            (true, false) => Platform::Host,
+            (false, true) => Platform::Target,
+            (false, false) => Platform::Unknown,
+        }
+    }
+
+    /// Returns the package URL of this dependency.
+    pub fn purl(&self) -> String {
+        // '+' separates build metadata in versions but is reserved in package URLs.
+        format!("pkg:cargo/{}@{}", self.name, self.ver.replace('+', "%2B"))
+    }
+
+    /// Returns the parsed license expression of this dependency, if it has a valid one.
+    pub fn license_expr(&self) -> Option<LicenseExpr> {
+        self.license
+            .as_ref()
+            .and_then(|license| LicenseExpr::parse(license).ok())
+    }
+
+    pub fn license_family(&self) -> LicenseFamily {
+        match (&self.license, &self.license_file) {
+            (Some(_), _) => self
+                .license_expr()
+                .map_or(LicenseFamily::Unknown, |expr| expr.family()),
+            (None, Some(_)) => LicenseFamily::Unknown,
+            (None, None) => LicenseFamily::Missing,
+        }
+    }
+
+    /// The license as displayed in reports.
+    pub fn license_name(&self) -> String {
+        match (&self.license, &self.license_file) {
+            (Some(license), _) => license.clone(),
+            (None, Some(file)) => format!("custom ({})", file),
+            (None, None) => "none".into(),
+        }
+    }
+
+    /// The name displayed on the node of this dependency.
+    pub fn display_name(&self, cfg: &Config) -> String {
+        if self.force_write_ver || cfg.include_vers {
             format!("{} v{}", self.name, self.ver)
         } else {
             self.name.clone()
-        };
+        }
+    }
+
+    /// The DOT attributes of the node of this dependency, other than its label.
+    pub fn attrs(&self, cfg: &Config, i: usize) -> Attrs {
+        let mut attrs = cfg.theme.kind(self.kind()).node.clone();
+        if cfg.color_by_license {
+            attrs.set("color", self.license_family().color());
+        }
+
+        if i == 0 {
+            attrs.merge(&cfg.theme.root);
+        } else if cfg.host_target {
+            attrs.set("shape", self.platform().shape());
+        }
+
+        let annotation = self.annotation(cfg);
+        if let Some(annotation) = annotation {
+            annotation.apply(&mut attrs);
+        }
+        // Banned and vulnerable deps keep their fill, so highlighting only draws their outline and
+        // dimming only fades their outline.
+        if self.is_highlighted && annotation != Some(Annotation::Highlighted) {
+            attrs.set("color", "gold");
+            attrs.set("penwidth", "3");
+        }
+        if self.is_dimmed {
+            attrs.set("color", "gray80");
+            if annotation.is_none() {
+                attrs.set("fontcolor", "gray60");
+            }
+        }
 
-        let shape = if i == 0 { ", shape=box" } else { "" };
+        attrs
+    }
 
-        match self.kind() {
-            DepKind::Regular => writeln!(w, " [label=\"{}\"{}];", name, shape),
-            DepKind::Build => writeln!(w, " [label=\"{}\", color=purple];", name),
-            DepKind::Dev => writeln!(w, " [label=\"{}\", color=blue];", name),
-            DepKind::Optional => writeln!(w, " [label=\"{}\", color=red];", name),
-            _ => writeln!(w, " [label=\"{}\", color=orange];", name),
+    /// The highlight drawn on the node of this dependency, if any.
+    pub fn annotation(&self, cfg: &Config) -> Option<Annotation> {
+        if self.is_banned {
+            Some(Annotation::Banned)
+        } else if self.is_vulnerable {
+            Some(Annotation::Vulnerable)
+        } else if self.is_highlighted {
+            Some(Annotation::Highlighted)
+        } else if self.is_dimmed {
+            None
+        } else if cfg.shade_outdated && self.newer_compatible.is_some() {
+            Some(Annotation::CompatibleUpdate)
+        } else if cfg.shade_outdated && self.newer_incompatible.is_some() {
+            Some(Annotation::IncompatibleUpdate)
+        } else {
+            None
+        }
+    }
+
+    pub fn label<W: Write>(&self, w: &mut W, cfg: &Config, i: usize) -> Result<()> {
+        let name = dot_escape(&self.display_name(cfg));
+        let attrs = self.attrs(cfg, i);
+
+        if attrs.is_empty() {
+            writeln!(w, " [label=\"{}\"];", name)
+        } else {
+            writeln!(w, " [label=\"{}\", {}];", name, attrs)
         }
     }
 }
@@ -0,0 +1,214 @@
+use crate::dep::DepKind;
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use crate::project::DeclaredDepsMap;
+use crate::util::xml_escape;
+use std::io::Write;
+
+/// The attributes declared for nodes, as (title, type) pairs. Attributes are referred to by their
+/// index in this list.
+const NODE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("name", "string"),
+    ("version", "string"),
+    ("kind", "string"),
+    ("regular", "boolean"),
+    ("build", "boolean"),
+    ("dev", "boolean"),
+    ("optional", "boolean"),
+    ("is_root", "boolean"),
+    ("source", "string"),
+    ("license", "string"),
+    ("depth", "integer"),
+];
+
+/// Writes the graph in the GEXF format used by Gephi. Nodes and edges are colored like in the DOT
+/// output.
+pub fn render<W: Write>(
+    dg: &DepGraph,
+    output: &mut W,
+    root_deps_map: &DeclaredDepsMap,
+) -> CliResult<()> {
+    writeln!(output, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        output,
+        "<gexf xmlns=\"http://gexf.net/1.3\" xmlns:viz=\"http://gexf.net/1.3/viz\" version=\"1.3\">"
+    )?;
+    writeln!(output, "\t<meta>")?;
+    writeln!(output, "\t\t<creator>cargo-deps</creator>")?;
+    if let Some(root) = dg.nodes.first() {
+        writeln!(
+            output,
+            "\t\t<description>Dependencies of {}</description>",
+            xml_escape(&root.name)
+        )?;
+    }
+    writeln!(output, "\t</meta>")?;
+    writeln!(
+        output,
+        "\t<graph defaultedgetype=\"directed\" mode=\"static\">"
+    )?;
+
+    writeln!(output, "\t\t<attributes class=\"node\">")?;
+    for (id, (title, ty)) in NODE_ATTRIBUTES.iter().enumerate() {
+        writeln!(
+            output,
+            "\t\t\t<attribute id=\"{}\" title=\"{}\" type=\"{}\"/>",
+            id, title, ty
+        )?;
+    }
+    writeln!(output, "\t\t</attributes>")?;
+    writeln!(output, "\t\t<attributes class=\"edge\">")?;
+    writeln!(
+        output,
+        "\t\t\t<attribute id=\"0\" title=\"kind\" type=\"string\"/>"
+    )?;
+    writeln!(output, "\t\t</attributes>")?;
+
+    writeln!(output, "\t\t<nodes>")?;
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        let values = [
+            Some(dep.name.clone()),
+            Some(dep.ver.clone()),
+            Some(format!("{:?}", dep.kind())),
+            Some(dep.is_regular.to_string()),
+            Some(dep.is_build.to_string()),
+            Some(dep.is_dev.to_string()),
+            Some(dep.is_optional.to_string()),
+            Some((i == 0).to_string()),
+            dep.source.clone(),
+            dep.license.clone(),
+            dep.depth.map(|d| d.to_string()),
+        ];
+
+        writeln!(
+            output,
+            "\t\t\t<node id=\"n{}\" label=\"{}\">",
+            i,
+            xml_escape(&dep.display_name(&dg.cfg))
+        )?;
+        writeln!(output, "\t\t\t\t<attvalues>")?;
+        for (id, value) in values.iter().enumerate() {
+            if let Some(value) = value {
+                writeln!(
+                    output,
+                    "\t\t\t\t\t<attvalue for=\"{}\" value=\"{}\"/>",
+                    id,
+                    xml_escape(value)
+                )?;
+            }
+        }
+        writeln!(output, "\t\t\t\t</attvalues>")?;
+        write_color(output, dep.kind(), "\t\t\t\t")?;
+        if i == 0 {
+            writeln!(output, "\t\t\t\t<viz:shape value=\"square\"/>")?;
+        }
+        writeln!(output, "\t\t\t</node>")?;
+    }
+    writeln!(output, "\t\t</nodes>")?;
+
+    writeln!(output, "\t\t<edges>")?;
+    for (i, ed) in dg.edges.iter().enumerate() {
+        let kind = ed.kind(dg, root_deps_map);
+        writeln!(
+            output,
+            "\t\t\t<edge id=\"e{}\" source=\"n{}\" target=\"n{}\">",
+            i, ed.0, ed.1
+        )?;
+        writeln!(output, "\t\t\t\t<attvalues>")?;
+        writeln!(
+            output,
+            "\t\t\t\t\t<attvalue for=\"0\" value=\"{:?}\"/>",
+            kind
+        )?;
+        writeln!(output, "\t\t\t\t</attvalues>")?;
+        write_color(output, kind, "\t\t\t\t")?;
+        writeln!(output, "\t\t\t</edge>")?;
+    }
+    writeln!(output, "\t\t</edges>")?;
+
+    writeln!(output, "\t</graph>")?;
+    writeln!(output, "</gexf>")?;
+
+    Ok(())
+}
+
+fn write_color<W: Write>(output: &mut W, kind: DepKind, indent: &str) -> CliResult<()> {
+    let (r, g, b) = kind.rgb();
+    writeln!(
+        output,
+        "{}<viz:color r=\"{}\" g=\"{}\" b=\"{}\"/>",
+        indent, r, g, b
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dep::DepKind::{Build, Optional, Regular};
+    use std::collections::HashMap;
+
+    #[test]
+    fn attributes_and_colors() {
+        let mut dg = DepGraph::new(Config::from_args(&["--optional-deps"]));
+        let root = dg.find_or_add("root", "1.0.0");
+        let serde = dg.add_child(root, "serde", "1.0.0");
+        dg.add_child(root, "cc", "1.0.0");
+        dg.add_child(root, "rand", "0.6.5");
+        dg.nodes[serde].license = Some("MIT".into());
+        let mut declared = HashMap::new();
+        declared.insert("serde".to_string(), vec![Regular]);
+        declared.insert("cc".to_string(), vec![Build]);
+        declared.insert("rand".to_string(), vec![Optional]);
+        dg.set_resolved_kind(&declared);
+
+        let mut output = vec![];
+        render(&dg, &mut output, &declared).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        for expected in [
+            "\t\t<attributes class=\"node\">\n\
+             \t\t\t<attribute id=\"0\" title=\"name\" type=\"string\"/>\n",
+            "\t\t\t<attribute id=\"10\" title=\"depth\" type=\"integer\"/>\n\
+             \t\t</attributes>\n\
+             \t\t<attributes class=\"edge\">\n\
+             \t\t\t<attribute id=\"0\" title=\"kind\" type=\"string\"/>\n\
+             \t\t</attributes>\n",
+            "\t\t\t<node id=\"n0\" label=\"root\">\n",
+            "\t\t\t\t<viz:color r=\"0\" g=\"0\" b=\"0\"/>\n\
+             \t\t\t\t<viz:shape value=\"square\"/>\n",
+            "\t\t\t<node id=\"n1\" label=\"serde\">\n\
+             \t\t\t\t<attvalues>\n\
+             \t\t\t\t\t<attvalue for=\"0\" value=\"serde\"/>\n\
+             \t\t\t\t\t<attvalue for=\"1\" value=\"1.0.0\"/>\n\
+             \t\t\t\t\t<attvalue for=\"2\" value=\"Regular\"/>\n\
+             \t\t\t\t\t<attvalue for=\"3\" value=\"true\"/>\n\
+             \t\t\t\t\t<attvalue for=\"4\" value=\"false\"/>\n\
+             \t\t\t\t\t<attvalue for=\"5\" value=\"false\"/>\n\
+             \t\t\t\t\t<attvalue for=\"6\" value=\"false\"/>\n\
+             \t\t\t\t\t<attvalue for=\"7\" value=\"false\"/>\n\
+             \t\t\t\t\t<attvalue for=\"9\" value=\"MIT\"/>\n\
+             \t\t\t\t</attvalues>\n",
+            "\t\t\t<edge id=\"e1\" source=\"n0\" target=\"n2\">\n\
+             \t\t\t\t<attvalues>\n\
+             \t\t\t\t\t<attvalue for=\"0\" value=\"Build\"/>\n\
+             \t\t\t\t</attvalues>\n\
+             \t\t\t\t<viz:color r=\"128\" g=\"0\" b=\"128\"/>\n",
+            "\t\t\t<edge id=\"e2\" source=\"n0\" target=\"n3\">\n\
+             \t\t\t\t<attvalues>\n\
+             \t\t\t\t\t<attvalue for=\"0\" value=\"Optional\"/>\n\
+             \t\t\t\t</attvalues>\n\
+             \t\t\t\t<viz:color r=\"255\" g=\"0\" b=\"0\"/>\n",
+        ] {
+            assert!(
+                output.contains(expected),
+                "{}\nnot in:\n{}",
+                expected,
+                output
+            );
+        }
+        assert_eq!(output.matches("<viz:color ").count(), 4 + 3);
+        assert!(output.ends_with("\t\t</edges>\n\t</graph>\n</gexf>\n"));
+    }
+}
@@ -1,9 +1,12 @@
-use crate::config::Config;
+use crate::config::{Config, Format};
 use crate::dep::{DepKind, ResolvedDep};
-use crate::error::CliResult;
+use crate::error::{CliError, CliResult};
+use crate::index::{self, CrateIndex};
 use crate::project::DeclaredDepsMap;
-use std::collections::HashMap;
-use std::fmt;
+use crate::theme::Attrs;
+use crate::util::{self, dot_escape};
+use crate::{advisory, bans, cyclonedx, gexf, graphml, html, legend, license, plantuml, spdx, svg};
+use std::collections::{HashMap, VecDeque};
 use std::io::{self, Write};
 
 pub type Node = usize;
@@ -12,12 +15,8 @@ pub type Node = usize;
 pub struct Edge(pub Node, pub Node);
 
 impl Edge {
-    pub fn label<W: Write>(
-        &self,
-        w: &mut W,
-        dg: &DepGraph,
-        root_deps_map: &DeclaredDepsMap,
-    ) -> io::Result<()> {
+    /// The kind of this edge, which depends on the kinds of both of its nodes.
+    pub fn kind(&self, dg: &DepGraph, root_deps_map: &DeclaredDepsMap) -> DepKind {
         use crate::dep::DepKind::{Build, Dev, Optional, Regular, Unknown};
 
         let parent = dg.get(self.0).unwrap().kind();
@@ -45,19 +44,40 @@ impl Edge {
         };
 
         match (parent, child) {
-            (Regular, Regular) => writeln!(w, ";"),
-            (Build, _) | (Regular, Build) => writeln!(w, " [color=purple, style=dashed];"),
-            (Dev, _) | (Regular, Dev) => writeln!(w, " [color=blue, style=dashed];"),
-            (Optional, _) | (Regular, Optional) => writeln!(w, " [color=red, style=dashed];"),
-            _ => writeln!(w, " [color=orange, style=dashed];"),
+            (Regular, Regular) => Regular,
+            (Build, _) | (Regular, Build) => Build,
+            (Dev, _) | (Regular, Dev) => Dev,
+            (Optional, _) | (Regular, Optional) => Optional,
+            _ => Unknown,
         }
     }
-}
 
-impl fmt::Display for Edge {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let &Edge(il, ir) = self;
-        write!(f, "n{} -> n{}", il, ir)
+    /// The DOT attributes of this edge: those of its kind, made bold if it touches a highlighted
+    /// node, or dimmed.
+    pub fn attrs(&self, dg: &DepGraph, root_deps_map: &DeclaredDepsMap) -> Attrs {
+        let mut attrs = dg.cfg.theme.kind(self.kind(dg, root_deps_map)).edge.clone();
+        let (parent, child) = (&dg.nodes[self.0], &dg.nodes[self.1]);
+        if parent.is_highlighted || child.is_highlighted {
+            attrs.add_style("bold");
+            attrs.set("penwidth", "2");
+        } else if dg.cfg.dim_others && dg.cfg.highlight.is_some() {
+            attrs.set("color", "gray80");
+        }
+        attrs
+    }
+
+    pub fn label<W: Write>(
+        &self,
+        w: &mut W,
+        dg: &DepGraph,
+        root_deps_map: &DeclaredDepsMap,
+    ) -> io::Result<()> {
+        let attrs = self.attrs(dg, root_deps_map);
+        if attrs.is_empty() {
+            writeln!(w, ";")
+        } else {
+            writeln!(w, " [{}];", attrs)
+        }
     }
 }
 
@@ -66,6 +86,8 @@ pub struct DepGraph {
     pub nodes: Vec<ResolvedDep>,
     pub edges: Vec<Edge>,
     pub cfg: Config,
+    /// Maps the name and then the version of each dependency to its node.
+    index: HashMap<String, HashMap<String, Node>>,
 }
 
 impl DepGraph {
@@ -74,6 +96,7 @@ impl DepGraph {
             nodes: vec![],
             edges: vec![],
             cfg,
+            index: HashMap::new(),
         }
     }
 
@@ -81,52 +104,88 @@ impl DepGraph {
     pub fn set_resolved_kind(&mut self, declared_deps_map: &HashMap<String, Vec<DepKind>>) {
         self.nodes[0].is_regular = true;
 
-        // Make sure to process edges from the root node first.
-        // Sorts by ID of first node first, then by second node.
-        self.edges.sort();
+        let children = self.children();
 
-        // FIXME: We repeat the following step several times to ensure that the kinds are propogated
-        // to all nodes. The surefire way to handle this would be to do a proper topological sort.
-        for _ in 0..10 {
-            for ed in self.edges.iter() {
-                if ed.0 == 0 {
-                    // If this is an edge from the root node,
-                    // set the kind based on how the dependency is declared in the manifest file.
-                    if let Some(kinds) = declared_deps_map.get(&*self.nodes[ed.1].name) {
-                        for kind in kinds {
-                            match *kind {
-                                DepKind::Regular => self.nodes[ed.1].is_regular = true,
-                                DepKind::Build => self.nodes[ed.1].is_build = true,
-                                DepKind::Dev => self.nodes[ed.1].is_dev = true,
-                                DepKind::Optional => self.nodes[ed.1].is_optional = true,
-                                _ => (),
-                            }
-                        }
-                    }
-                } else {
-                    // If this is an edge from a dependency node, propagate the kind. This is a set
-                    // of flags because a dependency can appear several times in the graph, and the
-                    // kind of dependency may vary based on the path to that dependency. The flags
-                    // start at false, and once they become true, they stay true.
-                    // ResolvedDep::kind() will pick a kind based on their priority.
-
-                    if self.nodes[ed.0].is_regular {
-                        self.nodes[ed.1].is_regular = true;
-                    }
-                    if self.nodes[ed.0].is_build {
-                        self.nodes[ed.1].is_build = true;
-                    }
-                    if self.nodes[ed.0].is_dev {
-                        self.nodes[ed.1].is_dev = true;
-                    }
-                    if self.nodes[ed.0].is_optional {
-                        self.nodes[ed.1].is_optional = true;
+        // Edges from the root node set the kind based on how the dependency is declared in the
+        // manifest file. These nodes seed the worklist. Procedural macros are always compiled for
+        // the host, on top of the way they are declared.
+        let mut worklist = VecDeque::new();
+        for &child in &children[0] {
+            if let Some(kinds) = declared_deps_map.get(&*self.nodes[child].name) {
+                if self.nodes[child].is_proc_macro() {
+                    self.nodes[child].is_build = true;
+                }
+                for kind in kinds {
+                    match *kind {
+                        DepKind::Regular => self.nodes[child].is_regular = true,
+                        DepKind::Build => self.nodes[child].is_build = true,
+                        DepKind::Dev => self.nodes[child].is_dev = true,
+                        DepKind::Optional => self.nodes[child].is_optional = true,
+                        _ => (),
                     }
                 }
             }
+            worklist.push_back(child);
+        }
+
+        // Edges from a dependency node propagate the kind. This is a set of flags because a
+        // dependency can appear several times in the graph, and the kind of dependency may vary
+        // based on the path to that dependency. The flags start at false, and once they become
+        // true, they stay true. ResolvedDep::kind() will pick a kind based on their priority.
+        //
+        // A node is only revisited when one of its flags changes, so this reaches a fixed point
+        // even when the graph contains cycles.
+        while let Some(parent) = worklist.pop_front() {
+            if parent == 0 {
+                continue;
+            }
+
+            for &child in &children[parent] {
+                if child != 0 && self.propagate_kind(parent, child) {
+                    worklist.push_back(child);
+                }
+            }
         }
     }
 
+    /// Propagates the kind flags of `parent` to `child`, following cargo's rules: the dependencies
+    /// of a regular dependency are compiled in the same contexts as their parent, while build
+    /// dependencies are compiled for the host (`is_build`) in every context their parent is
+    /// compiled in. Procedural macros are compiled for the host on top of the way they are pulled
+    /// in, and their dependencies only for the host. Returns true if any flag of `child` changed.
+    fn propagate_kind(&mut self, parent: Node, child: Node) -> bool {
+        let (is_regular, is_build, is_dev, is_optional) = {
+            let p = &self.nodes[parent];
+            let c = &self.nodes[child];
+
+            let is_reached = p.is_regular || p.is_build || p.is_dev || p.is_optional;
+            let regular = p.has_regular_dep(&c.name);
+            let build = p.has_build_dep(&c.name);
+            if p.is_proc_macro() {
+                (false, is_reached, false, false)
+            } else {
+                (
+                    regular && p.is_regular,
+                    (regular && p.is_build) || ((build || c.is_proc_macro()) && is_reached),
+                    regular && p.is_dev,
+                    regular && p.is_optional,
+                )
+            }
+        };
+        let c = &mut self.nodes[child];
+        let changed = (is_regular && !c.is_regular)
+            || (is_build && !c.is_build)
+            || (is_dev && !c.is_dev)
+            || (is_optional && !c.is_optional);
+
+        c.is_regular |= is_regular;
+        c.is_build |= is_build;
+        c.is_dev |= is_dev;
+        c.is_optional |= is_optional;
+
+        changed
+    }
+
     /// Forces the version to be displayed on dependencies that have the same name (but a different
     /// version) as another dependency.
     pub fn show_version_on_duplicates(&mut self) {
@@ -185,66 +244,261 @@ impl DepGraph {
         None
     }
 
+    /// Returns the children of each node, indexed by node.
+    pub fn children(&self) -> Vec<Vec<Node>> {
+        let mut children = vec![vec![]; self.nodes.len()];
+        for &Edge(parent, child) in &self.edges {
+            children[parent].push(child);
+        }
+        children
+    }
+
+    /// Returns the length of the shortest path from the root node to each node, indexed by node.
+    /// Nodes that can't be reached from the root have no depth.
+    pub fn depths(&self) -> Vec<Option<usize>> {
+        let children = self.children();
+        let mut depths = vec![None; self.nodes.len()];
+        let mut queue = VecDeque::new();
+        if !self.nodes.is_empty() {
+            depths[0] = Some(0);
+            queue.push_back(0);
+        }
+
+        while let Some(node) = queue.pop_front() {
+            let depth = depths[node].map(|d| d + 1);
+            for &child in &children[node] {
+                if depths[child].is_none() {
+                    depths[child] = depth;
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        depths
+    }
+
+    /// Returns a shortest path of nodes from the root node to `node`, if it can be reached.
+    pub fn path_to(&self, node: Node) -> Option<Vec<Node>> {
+        let children = self.children();
+        let mut parents = vec![None; self.nodes.len()];
+        let mut queue = VecDeque::new();
+        queue.push_back(0);
+
+        while let Some(parent) = queue.pop_front() {
+            if parent == node {
+                let mut path = vec![node];
+                let mut n = node;
+                while let Some(p) = parents[n] {
+                    path.push(p);
+                    n = p;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &child in &children[parent] {
+                if child != 0 && parents[child].is_none() {
+                    parents[child] = Some(parent);
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Writes the path from the root node to `node` on an indented line, e.g. `app -> foo -> bar`.
+    pub fn write_path_to<W: Write>(&self, output: &mut W, node: Node) -> io::Result<()> {
+        if let Some(path) = self.path_to(node) {
+            let names = path
+                .iter()
+                .map(|&n| self.nodes[n].display_name(&self.cfg))
+                .collect::<Vec<_>>();
+            writeln!(output, "    {}", names.join(" -> "))?;
+        }
+        Ok(())
+    }
+
+    /// Removes the nodes that can't be reached from the root node.
     pub fn remove_orphans(&mut self) {
         let len = self.nodes.len();
         self.edges.retain(|&Edge(idl, idr)| idl < len && idr < len);
-        loop {
-            let mut removed = false;
-            let mut used = vec![false; self.nodes.len()];
-            used[0] = true;
-            for &Edge(_, idr) in &self.edges {
-                used[idr] = true;
-            }
-
-            for (id, &u) in used.iter().enumerate() {
-                if !u {
-                    self.nodes.remove(id);
-
-                    // Remove edges originating from the removed node
-                    self.edges.retain(|&Edge(origin, _)| origin != id);
-                    // Adjust edges to match the new node indexes
-                    for edge in self.edges.iter_mut() {
-                        if edge.0 > id {
-                            edge.0 -= 1;
-                        }
-                        if edge.1 > id {
-                            edge.1 -= 1;
-                        }
-                    }
-                    removed = true;
-                    break;
+
+        let children = self.children();
+        let mut reachable = vec![false; len];
+        let mut stack = vec![0];
+        reachable[0] = true;
+
+        while let Some(node) = stack.pop() {
+            for &child in &children[node] {
+                if !reachable[child] {
+                    reachable[child] = true;
+                    stack.push(child);
                 }
             }
-            if !removed {
-                break;
-            }
         }
+
+        self.retain_nodes(&reachable);
     }
 
-    fn remove_self_pointing(&mut self) {
-        loop {
-            let mut found = false;
-            let mut self_p = vec![false; self.edges.len()];
-            for (eid, &Edge(idl, idr)) in self.edges.iter().enumerate() {
-                if idl == idr {
-                    found = true;
-                    self_p[eid] = true;
-                    break;
+    /// Removes every node for which `keep` is false, along with its edges. The remaining nodes
+    /// keep their relative order and the edges are renumbered to match.
+    pub fn retain_nodes(&mut self, keep: &[bool]) {
+        let mut remap = vec![None; self.nodes.len()];
+        let kept = keep.iter().enumerate().filter(|&(_, &k)| k);
+        for (new_id, (id, _)) in kept.enumerate() {
+            remap[id] = Some(new_id);
+        }
+
+        let mut id = 0;
+        self.nodes.retain(|_| {
+            id += 1;
+            keep[id - 1]
+        });
+        self.edges = self
+            .edges
+            .iter()
+            .filter_map(|&Edge(idl, idr)| Some(Edge(remap[idl]?, remap[idr]?)))
+            .collect();
+
+        self.index.clear();
+        for id in 0..self.nodes.len() {
+            self.index_node(id);
+        }
+    }
+
+    /// Orders the nodes other than the root, which stays first, by name, version and source, and
+    /// the edges by the name, version and source of their parent and then of their child, so that
+    /// the same graph is always written the same way whatever the order of the lockfile.
+    pub fn sort_nodes(&mut self) {
+        let cmp = |a: &ResolvedDep, b: &ResolvedDep| {
+            a.name
+                .cmp(&b.name)
+                .then_with(|| a.cmp_version(b))
+                .then_with(|| a.source.cmp(&b.source))
+        };
+
+        let mut order = (1..self.nodes.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| cmp(&self.nodes[a], &self.nodes[b]));
+        order.insert(0, 0);
+
+        let mut remap = vec![0; self.nodes.len()];
+        for (new_id, &id) in order.iter().enumerate() {
+            remap[id] = new_id;
+        }
+
+        let mut nodes = self.nodes.drain(..).map(Some).collect::<Vec<_>>();
+        self.nodes = order.iter().map(|&id| nodes[id].take().unwrap()).collect();
+        for edge in &mut self.edges {
+            *edge = Edge(remap[edge.0], remap[edge.1]);
+        }
+        let nodes = &self.nodes;
+        self.edges.sort_by(|a, b| {
+            cmp(&nodes[a.0], &nodes[b.0]).then_with(|| cmp(&nodes[a.1], &nodes[b.1]))
+        });
+
+        self.index.clear();
+        for id in 0..self.nodes.len() {
+            self.index_node(id);
+        }
+    }
+
+    /// Removes the edges whose target can also be reached through a longer path from their source,
+    /// made of edges of the same kind, so that a regular edge isn't replaced by a build or dev path.
+    /// Each edge is only removed if the path remains without it, so cycles keep one way around.
+    /// With `keep_root_edges`, the edges of the root, which are declared in its manifest, are kept.
+    pub fn transitive_reduction(&mut self, keep_root_edges: bool, root_deps_map: &DeclaredDepsMap) {
+        let kinds = self
+            .edges
+            .iter()
+            .map(|ed| ed.kind(self, root_deps_map))
+            .collect::<Vec<_>>();
+        let mut out = vec![vec![]; self.nodes.len()];
+        for (e, &Edge(parent, child)) in self.edges.iter().enumerate() {
+            out[parent].push((e, child));
+        }
+
+        // This searches from the source of every edge, which is quadratic in the worst case, but
+        // the search stops once the target is found, and it takes about a tenth of a second on
+        // 1,500 crates (see `bench_transitive_reduction`). A node was visited by the current search
+        // if its stamp is the index of the edge plus one, so the stamps needn't be cleared.
+        let mut removed = vec![false; self.edges.len()];
+        let mut visited = vec![0; self.nodes.len()];
+        let mut stack = vec![];
+        for (e, &Edge(parent, child)) in self.edges.iter().enumerate() {
+            if keep_root_edges && parent == 0 {
+                continue;
+            }
+
+            let stamp = e + 1;
+            visited[parent] = stamp;
+            stack.clear();
+            stack.push(parent);
+            'search: while let Some(node) = stack.pop() {
+                for &(other, next) in &out[node] {
+                    if other != e
+                        && !removed[other]
+                        && kinds[other] == kinds[e]
+                        && visited[next] != stamp
+                    {
+                        if next == child {
+                            removed[e] = true;
+                            break 'search;
+                        }
+                        visited[next] = stamp;
+                        stack.push(next);
+                    }
                 }
             }
+        }
 
-            for (id, &u) in self_p.iter().enumerate() {
-                if u {
-                    self.edges.remove(id);
-                    break;
+        let mut e = 0;
+        self.edges.retain(|_| {
+            e += 1;
+            !removed[e - 1]
+        });
+    }
+
+    /// Highlights the nodes whose name, or `name@version`, matches one of the glob `patterns`. With
+    /// `dim_others`, the nodes that aren't highlighted or next to a highlighted node are dimmed.
+    pub fn highlight(&mut self, patterns: &[String], dim_others: bool) {
+        for dep in &mut self.nodes {
+            let id = format!("{}@{}", dep.name, dep.ver);
+            dep.is_highlighted = patterns
+                .iter()
+                .any(|p| util::glob_match(p, &dep.name) || util::glob_match(p, &id));
+        }
+
+        if dim_others {
+            let mut near = self
+                .nodes
+                .iter()
+                .map(|dep| dep.is_highlighted)
+                .collect::<Vec<_>>();
+            for &Edge(parent, child) in &self.edges {
+                if self.nodes[parent].is_highlighted || self.nodes[child].is_highlighted {
+                    near[parent] = true;
+                    near[child] = true;
                 }
             }
-            if !found {
-                break;
+            for (dep, near) in self.nodes.iter_mut().zip(near) {
+                dep.is_dimmed = !near;
             }
         }
     }
 
+    /// Records the node `id` in the index under its name and version.
+    fn index_node(&mut self, id: Node) {
+        let dep = &self.nodes[id];
+        self.index
+            .entry(dep.name.clone())
+            .or_default()
+            .insert(dep.ver.clone(), id);
+    }
+
+    fn remove_self_pointing(&mut self) {
+        self.edges.retain(|&Edge(idl, idr)| idl != idr);
+    }
+
     pub fn set_root(&mut self, name: &str, ver: &str) -> bool {
         let root_id = if let Some(i) = self.find(name, ver) {
             i
@@ -258,6 +512,8 @@ impl DepGraph {
 
         // Swap with 0
         self.nodes.swap(0, root_id);
+        self.index_node(0);
+        self.index_node(root_id);
 
         // Adjust edges
         for edge in self.edges.iter_mut() {
@@ -276,12 +532,7 @@ impl DepGraph {
     }
 
     pub fn find(&self, name: &str, ver: &str) -> Option<usize> {
-        for (i, d) in self.nodes.iter().enumerate() {
-            if d.name == name && d.ver == ver {
-                return Some(i);
-            }
-        }
-        None
+        self.index.get(name)?.get(ver).cloned()
     }
 
     pub fn find_or_add(&mut self, name: &str, ver: &str) -> usize {
@@ -290,7 +541,9 @@ impl DepGraph {
         }
         self.nodes
             .push(ResolvedDep::new(name.to_owned(), ver.to_owned()));
-        self.nodes.len() - 1
+        let id = self.nodes.len() - 1;
+        self.index_node(id);
+        id
     }
 
     pub fn render_to<W: Write>(
@@ -304,8 +557,186 @@ impl DepGraph {
             self.remove_orphans();
         }
         self.remove_self_pointing();
+        for (node, depth) in self.depths().into_iter().enumerate() {
+            self.nodes[node].depth = depth;
+        }
+        if self.cfg.transitive_reduction {
+            self.transitive_reduction(self.cfg.keep_root_edges, root_deps_map);
+        }
+        if self.cfg.sort {
+            self.sort_nodes();
+        }
+
+        if let Some(policy) = &self.cfg.ban_policy {
+            for i in 0..self.nodes.len() {
+                self.nodes[i].is_banned = policy.find(&self.nodes[i]).is_some();
+            }
+        }
+
+        if let Some(db) = &self.cfg.advisory_db {
+            for i in 0..self.nodes.len() {
+                self.nodes[i].is_vulnerable = !db.find(&self.nodes[i]).is_empty();
+            }
+        }
+
+        if self.cfg.format == Format::Outdated || self.cfg.shade_outdated {
+            CrateIndex::new(self.cfg.index_path.as_deref()).annotate(&mut self);
+        }
+
+        if let Some(patterns) = self.cfg.highlight.clone() {
+            self.highlight(&patterns, self.cfg.dim_others);
+        }
+
+        if self.cfg.is_check() {
+            return self.check(output);
+        }
+
+        match self.cfg.format {
+            Format::Dot => self.render_dot(output, root_deps_map),
+            Format::Svg => svg::render(&self, output, root_deps_map),
+            Format::Html => html::render(&self, output, root_deps_map),
+            Format::GraphMl => graphml::render(&self, output, root_deps_map),
+            Format::Gexf => gexf::render(&self, output, root_deps_map),
+            Format::PlantUml => plantuml::render(&self, output, root_deps_map),
+            Format::CycloneDxJson => cyclonedx::render_json(&self, output),
+            Format::CycloneDxXml => cyclonedx::render_xml(&self, output),
+            Format::Spdx => spdx::render_tag_value(&self, output, root_deps_map),
+            Format::SpdxJson => spdx::render_json(&self, output, root_deps_map),
+            Format::Licenses => license::render_summary(&self, output),
+            Format::Outdated => index::render_outdated(&self, output),
+        }
+    }
+
+    /// Runs the checks enabled in the config and writes their violations. Returns an error if
+    /// there are any, so that the program exits with a non-zero status.
+    fn check<W: Write>(&self, output: &mut W) -> CliResult<()> {
+        let crates = |n: usize| format!("{} crate{}", n, if n == 1 { "" } else { "s" });
+        let mut errors = vec![];
+
+        if let Some(policy) = &self.cfg.license_policy {
+            let violations = license::check(self, output, policy)?;
+            if violations > 0 {
+                errors.push(format!(
+                    "{} violating the license policy",
+                    crates(violations)
+                ));
+            }
+        }
+        if let (true, Some(policy)) = (self.cfg.check_bans, &self.cfg.ban_policy) {
+            let banned = bans::check(self, output, policy)?;
+            if banned > 0 {
+                errors.push(format!("{} banned", crates(banned)));
+            }
+        }
+
+        if let (true, Some(db)) = (self.cfg.audit, &self.cfg.advisory_db) {
+            let vulnerable = advisory::check(self, output, db)?;
+            if vulnerable > 0 {
+                errors.push(format!("{} with security advisories", crates(vulnerable)));
+            }
+        }
 
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CliError::Generic(format!("Found {}", errors.join(", "))))
+        }
+    }
+
+    /// The DOT identifiers of the nodes: `n<index>`, or `"name@version"` with `--stable-ids`, which
+    /// don't change when nodes are added or reordered. Crates whose name and version are shared
+    /// with a crate of another source, such as a git fork of a registry crate, get the source as a
+    /// suffix, e.g. `"name@version (git+https://...)"`, so that they aren't merged.
+    fn node_ids(&self) -> Vec<String> {
+        if !self.cfg.stable_ids {
+            return (0..self.nodes.len())
+                .map(|node| format!("n{}", node))
+                .collect();
+        }
+
+        let mut counts = HashMap::new();
+        for dep in &self.nodes {
+            *counts.entry((&*dep.name, &*dep.ver)).or_insert(0) += 1;
+        }
+        self.nodes
+            .iter()
+            .map(|dep| {
+                let mut id = format!("{}@{}", dep.name, dep.ver);
+                if counts[&(&*dep.name, &*dep.ver)] > 1 {
+                    id = format!("{} ({})", id, dep.source.as_deref().unwrap_or("path"));
+                }
+                format!("\"{}\"", dot_escape(&id))
+            })
+            .collect()
+    }
+
+    /// Writes a `rank=same` group for each depth, so that the nodes at the same shortest distance
+    /// from the root are placed side by side. With `--rank-labels`, a chain of labels down the
+    /// side names each layer.
+    fn render_ranks<W: Write>(&self, output: &mut W, ids: &[String]) -> io::Result<()> {
+        let mut layers: Vec<Vec<Node>> = vec![];
+        for (node, dep) in self.nodes.iter().enumerate() {
+            if let Some(depth) = dep.depth {
+                if layers.len() <= depth {
+                    layers.resize(depth + 1, vec![]);
+                }
+                layers[depth].push(node);
+            }
+        }
+
+        for (depth, nodes) in layers.iter().enumerate() {
+            write!(output, "\t{{rank=same;")?;
+            if self.cfg.rank_labels {
+                write!(output, " rank_{};", depth)?;
+            }
+            for &node in nodes {
+                write!(output, " {};", ids[node])?;
+            }
+            writeln!(output, "}}")?;
+        }
+
+        if self.cfg.rank_labels {
+            for depth in 0..layers.len() {
+                let label = match depth {
+                    0 => "root".into(),
+                    1 => "direct".into(),
+                    _ => format!("level {}", depth),
+                };
+                writeln!(
+                    output,
+                    "\trank_{} [label=\"{}\", shape=plaintext];",
+                    depth, label
+                )?;
+            }
+            for depth in 1..layers.len() {
+                writeln!(
+                    output,
+                    "\trank_{} -> rank_{} [style=invis];",
+                    depth - 1,
+                    depth
+                )?;
+            }
+        }
+        writeln!(output)
+    }
+
+    fn render_dot<W: Write>(
+        &self,
+        output: &mut W,
+        root_deps_map: &DeclaredDepsMap,
+    ) -> CliResult<()> {
         writeln!(output, "digraph dependencies {{")?;
+        let theme = &self.cfg.theme;
+        let ids = self.node_ids();
+        for (name, attrs) in &[
+            ("graph", &theme.graph),
+            ("node", &theme.node),
+            ("edge", &theme.edge),
+        ] {
+            if !attrs.is_empty() {
+                writeln!(output, "\t{} [{}];", name, attrs)?;
+            }
+        }
         for (i, dep) in self.nodes.iter().enumerate() {
             if let Some(sub_deps) = &self.cfg.subgraph {
                 if sub_deps.contains(&dep.name) {
@@ -314,7 +745,7 @@ impl DepGraph {
                 }
             }
 
-            write!(output, "\tn{}", i)?;
+            write!(output, "\t{}", ids[i])?;
             dep.label(output, &self.cfg, i)?;
         }
         writeln!(output)?;
@@ -322,15 +753,16 @@ impl DepGraph {
         if let Some(sub_deps) = &self.cfg.subgraph {
             writeln!(output, "\tsubgraph cluster_subgraph {{")?;
             if let Some(sub_name) = &self.cfg.subgraph_name {
-                writeln!(output, "\t\tlabel=\"{}\";", sub_name)?;
+                writeln!(output, "\t\tlabel=\"{}\";", dot_escape(sub_name))?;
+            }
+            for (key, value) in theme.subgraph.iter() {
+                writeln!(output, "\t\t{}={};", key, value)?;
             }
-            writeln!(output, "\t\tcolor=brown;")?;
-            writeln!(output, "\t\tstyle=dashed;")?;
             writeln!(output)?;
 
             for (i, dep) in self.nodes.iter().enumerate() {
                 if sub_deps.contains(&dep.name) {
-                    write!(output, "\t\tn{}", i)?;
+                    write!(output, "\t\t{}", ids[i])?;
                     dep.label(output, &self.cfg, i)?;
                 }
             }
@@ -338,12 +770,567 @@ impl DepGraph {
             writeln!(output, "\t}}\n")?;
         }
 
+        if self.cfg.rank_by_depth {
+            self.render_ranks(output, &ids)?;
+        }
+
+        if self.cfg.legend {
+            legend::render_dot(self, output, root_deps_map)?;
+        }
+
         for ed in &self.edges {
-            write!(output, "\t{}", ed)?;
-            ed.label(output, &self, root_deps_map)?;
+            write!(output, "\t{} -> {}", ids[ed.0], ids[ed.1])?;
+            ed.label(output, self, root_deps_map)?;
         }
         writeln!(output, "}}")?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dep::DepKind::{Build, Dev, Regular};
+    use crate::dep::Platform;
+    use crate::manifest::PackageManifest;
+
+    /// A graph rooted at `root`, with the given edges between crates of version 1.0.0.
+    fn graph(edges: &[(&str, &str)]) -> DepGraph {
+        let mut dg = DepGraph::new(Config::from_args(&[]));
+        dg.find_or_add("root", "1.0.0");
+        for (parent, child) in edges {
+            let parent = dg.find_or_add(parent, "1.0.0");
+            dg.add_child(parent, child, "1.0.0");
+        }
+        dg
+    }
+
+    fn declared(deps: &[(&str, DepKind)]) -> HashMap<String, Vec<DepKind>> {
+        let mut map = HashMap::new();
+        for (name, kind) in deps {
+            map.entry(name.to_string())
+                .or_insert_with(Vec::new)
+                .push(*kind);
+        }
+        map
+    }
+
+    fn dep<'a>(dg: &'a DepGraph, name: &str) -> &'a ResolvedDep {
+        &dg.nodes[dg.find(name, "1.0.0").unwrap()]
+    }
+
+    #[test]
+    fn orphans_are_the_nodes_unreachable_from_the_root() {
+        // `x` and `y` form a cycle that nothing else depends on, so both have incoming edges but
+        // can't be reached from the root.
+        let mut dg = graph(&[
+            ("root", "a"),
+            ("a", "b"),
+            ("x", "y"),
+            ("y", "x"),
+            ("y", "b"),
+        ]);
+        dg.remove_orphans();
+
+        let names = dg.nodes.iter().map(|dep| &*dep.name).collect::<Vec<_>>();
+        assert_eq!(names, ["root", "a", "b"]);
+        assert_eq!(dg.edges, [Edge(0, 1), Edge(1, 2)]);
+        assert_eq!(dg.find("b", "1.0.0"), Some(2));
+        assert_eq!(dg.find("x", "1.0.0"), None);
+    }
+
+    /// Times orphan removal on a synthetic graph of 200,000 nodes, half of them unreachable. Run
+    /// with `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_remove_orphans() {
+        use std::time::Instant;
+
+        const NODES: usize = 200_000;
+        let mut dg = DepGraph::new(Config::from_args(&[]));
+        for i in 0..NODES {
+            dg.find_or_add(&format!("c{}", i), "1.0.0");
+        }
+        // Each node of the reachable half depends on a few later nodes of that half, and the
+        // unreachable half forms chains and cycles among itself, with edges into the first half.
+        let mut seed = 1u64;
+        let mut random = |n: usize| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) as usize % n
+        };
+        let half = NODES / 2;
+        for i in 0..half - 1 {
+            dg.edges.push(Edge(i, i + 1));
+            for _ in 0..4 {
+                dg.edges.push(Edge(i, i + 1 + random(half - i - 1)));
+            }
+        }
+        for i in half..NODES {
+            dg.edges.push(Edge(i, half + random(half)));
+            dg.edges.push(Edge(i, random(half)));
+            dg.edges.push(Edge(i, i));
+        }
+        dg.edges.sort();
+        dg.edges.dedup();
+        let edges = dg.edges.len();
+
+        let start = Instant::now();
+        dg.remove_orphans();
+        dg.remove_self_pointing();
+        let elapsed = start.elapsed();
+
+        assert_eq!(dg.nodes.len(), half);
+        println!(
+            "remove_orphans and remove_self_pointing on {} nodes and {} edges: {:?}",
+            NODES, edges, elapsed
+        );
+    }
+
+    /// Times the transitive reduction on a synthetic graph of 1,500 crates, about the size of the
+    /// largest dependency trees. Run with `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_transitive_reduction() {
+        use std::time::Instant;
+
+        const NODES: usize = 1_500;
+        let mut dg = DepGraph::new(Config::from_args(&[]));
+        for i in 0..NODES {
+            dg.find_or_add(&format!("c{}", i), "1.0.0");
+        }
+        // Each crate depends on the next one and on a few later crates, which are mostly implied.
+        let mut seed = 1u64;
+        let mut random = |n: usize| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) as usize % n
+        };
+        for i in 0..NODES - 1 {
+            dg.edges.push(Edge(i, i + 1));
+            for _ in 0..5 {
+                dg.edges.push(Edge(i, i + 1 + random(NODES - i - 1)));
+            }
+        }
+        dg.edges.sort();
+        dg.edges.dedup();
+        let root_children = dg
+            .edges
+            .iter()
+            .filter(|ed| ed.0 == 0)
+            .map(|ed| (&*dg.nodes[ed.1].name, Regular))
+            .collect::<Vec<_>>();
+        let root_deps = declared(&root_children);
+        dg.set_resolved_kind(&root_deps);
+        let edges = dg.edges.len();
+
+        let start = Instant::now();
+        dg.transitive_reduction(false, &root_deps);
+        let elapsed = start.elapsed();
+
+        assert_eq!(dg.edges.len(), NODES - 1);
+        println!(
+            "transitive_reduction on {} nodes and {} edges: {:?}",
+            NODES, edges, elapsed
+        );
+    }
+
+    #[test]
+    fn kind_propagates_down_deep_chains() {
+        let regular = (0..15).map(|i| format!("r{}", i)).collect::<Vec<_>>();
+        let build = (0..15).map(|i| format!("b{}", i)).collect::<Vec<_>>();
+        let mut edges = vec![("root", "r0"), ("root", "b0")];
+        for chain in &[&regular, &build] {
+            for pair in chain.windows(2) {
+                edges.push((&pair[0], &pair[1]));
+            }
+        }
+        let mut dg = graph(&edges);
+        dg.set_resolved_kind(&declared(&[("r0", Regular), ("b0", Build)]));
+
+        for name in &regular {
+            assert_eq!(dep(&dg, name).kind(), Regular, "{}", name);
+        }
+        for name in &build {
+            assert_eq!(dep(&dg, name).kind(), Build, "{}", name);
+            assert!(!dep(&dg, name).is_regular, "{}", name);
+        }
+    }
+
+    #[test]
+    fn kinds_of_all_paths_are_combined() {
+        let mut dg = graph(&[
+            ("root", "a"),
+            ("root", "b"),
+            ("root", "d"),
+            ("a", "shared"),
+            ("b", "shared"),
+            ("d", "shared"),
+            ("b", "host"),
+            ("d", "host"),
+        ]);
+        dg.set_resolved_kind(&declared(&[("a", Regular), ("b", Build), ("d", Dev)]));
+
+        let shared = dep(&dg, "shared");
+        assert!(shared.is_regular && shared.is_build && shared.is_dev);
+        assert_eq!(shared.kind(), Regular);
+
+        let host = dep(&dg, "host");
+        assert!(!host.is_regular && host.is_build && host.is_dev);
+        assert_eq!(host.kind(), Build);
+    }
+
+    #[test]
+    fn kind_propagates_around_cycles() {
+        let mut dg = graph(&[
+            ("root", "a"),
+            ("root", "z"),
+            ("a", "b"),
+            ("b", "c"),
+            ("c", "a"),
+            ("z", "c"),
+        ]);
+        dg.set_resolved_kind(&declared(&[("a", Regular), ("z", Build)]));
+
+        // `c` is also a dependency of the build dependency `z`, which carries over to the rest of
+        // the cycle.
+        for name in &["a", "b", "c"] {
+            let dep = dep(&dg, name);
+            assert!(dep.is_regular && dep.is_build, "{}", name);
+            assert!(!dep.is_dev && !dep.is_optional, "{}", name);
+        }
+    }
+
+    /// Attaches a manifest to `name` that declares the given regular and build dependencies.
+    fn set_manifest(dg: &mut DepGraph, name: &str, deps: &[&str], build_deps: &[&str]) {
+        let i = dg.find(name, "1.0.0").unwrap();
+        dg.nodes[i].manifest = Some(PackageManifest {
+            deps: deps.iter().map(|dep| dep.to_string()).collect(),
+            build_deps: build_deps.iter().map(|dep| dep.to_string()).collect(),
+            ..PackageManifest::default()
+        });
+    }
+
+    #[test]
+    fn regular_deps_of_build_deps_are_host_only() {
+        let mut dg = graph(&[("root", "b"), ("b", "c")]);
+        set_manifest(&mut dg, "b", &["c"], &[]);
+        dg.set_resolved_kind(&declared(&[("b", Build)]));
+
+        let c = dep(&dg, "c");
+        assert!(c.is_build && !c.is_regular);
+        assert_eq!(c.platform(), Platform::Host);
+    }
+
+    #[test]
+    fn build_deps_of_regular_deps_are_host_only() {
+        let mut dg = graph(&[("root", "a"), ("a", "cc"), ("cc", "x")]);
+        set_manifest(&mut dg, "a", &[], &["cc"]);
+        dg.set_resolved_kind(&declared(&[("a", Regular)]));
+
+        for name in &["cc", "x"] {
+            assert_eq!(dep(&dg, name).platform(), Platform::Host, "{}", name);
+        }
+    }
+
+    #[test]
+    fn deps_declared_as_regular_and_build_are_on_both_platforms() {
+        let mut dg = graph(&[("root", "a"), ("a", "c")]);
+        set_manifest(&mut dg, "a", &["c"], &["c"]);
+        dg.set_resolved_kind(&declared(&[("a", Regular)]));
+
+        let c = dep(&dg, "c");
+        assert!(c.is_regular && c.is_build);
+        assert_eq!(c.platform(), Platform::Both);
+    }
+
+    #[test]
+    fn proc_macros_are_forced_to_the_host() {
+        let mut dg = graph(&[
+            ("root", "derive"),
+            ("derive", "syn"),
+            ("root", "a"),
+            ("a", "m"),
+        ]);
+        set_manifest(&mut dg, "derive", &["syn"], &[]);
+        set_manifest(&mut dg, "a", &["m"], &[]);
+        for name in &["derive", "m"] {
+            let i = dg.find(name, "1.0.0").unwrap();
+            let manifest = dg.nodes[i]
+                .manifest
+                .get_or_insert_with(PackageManifest::default);
+            manifest.is_proc_macro = true;
+        }
+        dg.set_resolved_kind(&declared(&[("derive", Regular), ("a", Regular)]));
+
+        // Declared as a regular dependency by the root, and as a regular dependency of `a`. The
+        // dependencies of a procedural macro are only compiled for the host.
+        for name in &["derive", "m"] {
+            let dep = dep(&dg, name);
+            assert!(dep.is_build && dep.is_regular, "{}", name);
+            assert_eq!(dep.kind(), Regular, "{}", name);
+            assert_eq!(dep.platform(), Platform::Host, "{}", name);
+        }
+        let syn = dep(&dg, "syn");
+        assert!(syn.is_build && !syn.is_regular);
+        assert_eq!(syn.platform(), Platform::Host);
+    }
+
+    #[test]
+    fn dev_only_proc_macros_keep_their_kind() {
+        let mut dg = graph(&[("root", "derive"), ("derive", "syn")]);
+        set_manifest(&mut dg, "derive", &["syn"], &[]);
+        let i = dg.find("derive", "1.0.0").unwrap();
+        dg.nodes[i].manifest.as_mut().unwrap().is_proc_macro = true;
+        let root_deps = declared(&[("derive", Dev)]);
+        dg.set_resolved_kind(&root_deps);
+
+        let derive = dep(&dg, "derive");
+        assert!(derive.is_dev && derive.is_build && !derive.is_regular);
+        assert_eq!(derive.kind(), Dev);
+        assert_eq!(derive.platform(), Platform::Host);
+        assert_eq!(Edge(0, i).kind(&dg, &root_deps), Dev);
+
+        let syn = dep(&dg, "syn");
+        assert!(syn.is_build && !syn.is_dev);
+        assert_eq!(syn.platform(), Platform::Host);
+    }
+
+    #[test]
+    fn host_target_shapes_nodes_by_platform() {
+        let mut dg = DepGraph::new(Config::from_args(&[
+            "--host-target",
+            "--include-orphans",
+            "--build-deps",
+        ]));
+        dg.find_or_add("root", "1.0.0");
+        for (parent, child) in [
+            ("root", "a"),
+            ("root", "b"),
+            ("a", "s"),
+            ("b", "s"),
+            ("o", "a"),
+        ] {
+            let parent = dg.find_or_add(parent, "1.0.0");
+            dg.add_child(parent, child, "1.0.0");
+        }
+        let root_deps = declared(&[("a", Regular), ("b", Build)]);
+        dg.set_resolved_kind(&root_deps);
+
+        let mut output = vec![];
+        dg.render_to(&mut output, &root_deps).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let shape = |name: &str| {
+            let label = format!("[label=\"{}\"", name);
+            let line = output.lines().find(|line| line.contains(&label)).unwrap();
+            line.split("shape=")
+                .nth(1)
+                .unwrap()
+                .split(&[',', ']'][..])
+                .next()
+                .unwrap()
+                .to_owned()
+        };
+
+        assert_eq!(shape("root"), "box");
+        assert_eq!(shape("a"), "ellipse");
+        assert_eq!(shape("b"), "hexagon");
+        assert_eq!(shape("s"), "octagon");
+        assert_eq!(shape("o"), "diamond");
+    }
+
+    #[test]
+    fn banned_fill_is_kept_when_highlighted_or_dimmed() {
+        let mut dg = graph(&[("root", "a"), ("a", "b"), ("b", "c"), ("c", "d")]);
+        for name in ["a", "d"] {
+            let i = dg.find(name, "1.0.0").unwrap();
+            dg.nodes[i].is_banned = true;
+        }
+        dg.highlight(&["a".into()], true);
+        let attrs = |name: &str| {
+            let i = dg.find(name, "1.0.0").unwrap();
+            dg.nodes[i].attrs(&dg.cfg, i)
+        };
+
+        let a = attrs("a");
+        assert_eq!(a.get("fillcolor"), Some("crimson"));
+        assert_eq!(a.get("color"), Some("gold"));
+        assert_eq!(attrs("b").get("fillcolor"), None);
+
+        let d = attrs("d");
+        assert!(dep(&dg, "d").is_dimmed);
+        assert_eq!(d.get("fillcolor"), Some("crimson"));
+        assert_eq!(d.get("fontcolor"), Some("white"));
+        assert_eq!(d.get("color"), Some("gray80"));
+        assert_eq!(attrs("c").get("fontcolor"), Some("gray60"));
+    }
+
+    fn edge_names(dg: &DepGraph) -> Vec<(&str, &str)> {
+        dg.edges
+            .iter()
+            .map(|ed| (&*dg.nodes[ed.0].name, &*dg.nodes[ed.1].name))
+            .collect()
+    }
+
+    #[test]
+    fn reduction_removes_implied_edges() {
+        let edges = [
+            ("root", "a"),
+            ("root", "b"),
+            ("root", "c"),
+            ("a", "b"),
+            ("a", "c"),
+            ("b", "c"),
+        ];
+        let root_deps = declared(&[("a", Regular), ("b", Regular), ("c", Regular)]);
+
+        let mut dg = graph(&edges);
+        dg.set_resolved_kind(&root_deps);
+        dg.transitive_reduction(false, &root_deps);
+        assert_eq!(edge_names(&dg), [("root", "a"), ("a", "b"), ("b", "c")]);
+
+        let mut dg = graph(&edges);
+        dg.set_resolved_kind(&root_deps);
+        dg.transitive_reduction(true, &root_deps);
+        assert_eq!(
+            edge_names(&dg),
+            [
+                ("root", "a"),
+                ("root", "b"),
+                ("root", "c"),
+                ("a", "b"),
+                ("b", "c")
+            ]
+        );
+    }
+
+    #[test]
+    fn reduction_keeps_one_way_around_cycles() {
+        let mut dg = graph(&[
+            ("root", "a"),
+            ("a", "b"),
+            ("b", "a"),
+            ("a", "c"),
+            ("b", "c"),
+        ]);
+        let root_deps = declared(&[("a", Regular)]);
+        dg.set_resolved_kind(&root_deps);
+        dg.transitive_reduction(false, &root_deps);
+        assert_eq!(
+            edge_names(&dg),
+            [("root", "a"), ("a", "b"), ("b", "a"), ("b", "c")]
+        );
+    }
+
+    #[test]
+    fn reduction_only_follows_paths_of_the_same_kind() {
+        // `root -> a` is regular, and the path through the dev-dependency `t` doesn't imply it,
+        // but `t -> a` is implied by the dev path through `u`.
+        let mut dg = graph(&[
+            ("root", "a"),
+            ("root", "t"),
+            ("t", "u"),
+            ("t", "a"),
+            ("u", "a"),
+        ]);
+        let root_deps = declared(&[("a", Regular), ("t", Dev)]);
+        dg.set_resolved_kind(&root_deps);
+        dg.transitive_reduction(false, &root_deps);
+        assert_eq!(
+            edge_names(&dg),
+            [("root", "a"), ("root", "t"), ("t", "u"), ("u", "a")]
+        );
+    }
+
+    #[test]
+    fn depths_are_taken_before_the_reduction() {
+        let mut dg = DepGraph::new(Config::from_args(&[
+            "--rank-by-depth",
+            "--transitive-reduction",
+        ]));
+        dg.find_or_add("root", "1.0.0");
+        for (parent, child) in [("root", "a"), ("root", "c"), ("a", "c")] {
+            let parent = dg.find_or_add(parent, "1.0.0");
+            dg.add_child(parent, child, "1.0.0");
+        }
+        let root_deps = declared(&[("a", Regular), ("c", Regular)]);
+        dg.set_resolved_kind(&root_deps);
+
+        let mut output = vec![];
+        dg.render_to(&mut output, &root_deps).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(!output.contains("n0 -> n2"), "{}", output);
+        assert!(output.contains("{rank=same; n1; n2;}"), "{}", output);
+    }
+
+    #[test]
+    fn stable_ids_tell_sources_apart() {
+        let mut dg = DepGraph::new(Config::from_args(&["--stable-ids"]));
+        let root = dg.find_or_add("root", "1.0.0");
+        let registry = dg.add_child(root, "rand", "0.6.5");
+        dg.nodes[registry].source =
+            Some("registry+https://github.com/rust-lang/crates.io-index".into());
+        // A fork of the same version, which the index can't hold next to the registry crate.
+        let mut fork = ResolvedDep::new("rand".into(), "0.6.5".into());
+        fork.source = Some("git+https://github.com/fork/rand".into());
+        dg.nodes.push(fork);
+        dg.edges.push(Edge(root, dg.nodes.len() - 1));
+        dg.add_child(root, "serde", "1.0.100");
+        let root_deps = declared(&[("rand", Regular), ("serde", Regular)]);
+        dg.set_resolved_kind(&root_deps);
+
+        assert_eq!(
+            dg.node_ids(),
+            [
+                "\"root@1.0.0\"",
+                "\"rand@0.6.5 (registry+https://github.com/rust-lang/crates.io-index)\"",
+                "\"rand@0.6.5 (git+https://github.com/fork/rand)\"",
+                "\"serde@1.0.100\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn sorted_output_does_not_depend_on_the_lockfile_order() {
+        let edges = [
+            ("root", "1.0.0", "serde", "1.0.100"),
+            ("root", "1.0.0", "rand", "0.6.5"),
+            ("root", "1.0.0", "log", "0.4.8"),
+            ("rand", "0.6.5", "rand_core", "0.4.2"),
+            ("rand", "0.6.5", "rand_core", "0.3.1"),
+            ("rand_core", "0.3.1", "rand_core", "0.4.2"),
+            ("rand", "0.6.5", "libc", "0.2.60"),
+            ("log", "0.4.8", "cfg-if", "0.1.9"),
+            ("serde", "1.0.100", "serde_derive", "1.0.100"),
+            ("serde_derive", "1.0.100", "proc-macro2", "0.4.30"),
+            ("serde_derive", "1.0.100", "quote", "0.6.13"),
+            ("quote", "0.6.13", "proc-macro2", "0.4.30"),
+            ("proc-macro2", "0.4.30", "unicode-xid", "0.1.0"),
+            ("libc", "0.2.60", "cfg-if", "0.1.9"),
+        ];
+        let root_deps = declared(&[("serde", Regular), ("rand", Dev), ("log", Regular)]);
+        let render = |order: &[usize]| {
+            let mut dg = DepGraph::new(Config::from_args(&["--sort", "--dev-deps"]));
+            dg.find_or_add("root", "1.0.0");
+            for &i in order {
+                let (parent, parent_ver, child, child_ver) = edges[i];
+                let parent = dg.find_or_add(parent, parent_ver);
+                dg.add_child(parent, child, child_ver);
+            }
+            let mut output = vec![];
+            dg.render_to(&mut output, &root_deps).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let forward = (0..edges.len()).collect::<Vec<_>>();
+        let expected = render(&forward);
+        let mut order = forward.clone();
+        order.reverse();
+        assert_eq!(render(&order), expected);
+        for step in [3, 5, 9] {
+            let order = (0..edges.len())
+                .map(|i| i * step % edges.len())
+                .collect::<Vec<_>>();
+            assert_eq!(render(&order), expected);
+        }
+    }
+}
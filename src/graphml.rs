@@ -0,0 +1,170 @@
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use crate::project::DeclaredDepsMap;
+use crate::util::xml_escape;
+use std::io::Write;
+
+/// The attributes declared for nodes, as (id, type) pairs.
+const NODE_KEYS: &[(&str, &str)] = &[
+    ("name", "string"),
+    ("version", "string"),
+    ("kind", "string"),
+    ("color", "string"),
+    ("regular", "boolean"),
+    ("build", "boolean"),
+    ("dev", "boolean"),
+    ("optional", "boolean"),
+    ("is_root", "boolean"),
+    ("source", "string"),
+    ("license", "string"),
+    ("depth", "int"),
+];
+
+/// Writes the graph in the GraphML format, which can be read by yEd and Gephi among others.
+pub fn render<W: Write>(
+    dg: &DepGraph,
+    output: &mut W,
+    root_deps_map: &DeclaredDepsMap,
+) -> CliResult<()> {
+    writeln!(output, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        output,
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"http://graphml.graphdrawing.org/xmlns \
+         http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd\">"
+    )?;
+
+    for (id, ty) in NODE_KEYS {
+        writeln!(
+            output,
+            "\t<key id=\"{0}\" for=\"node\" attr.name=\"{0}\" attr.type=\"{1}\"/>",
+            id, ty
+        )?;
+    }
+    writeln!(
+        output,
+        "\t<key id=\"edge_kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>"
+    )?;
+    writeln!(
+        output,
+        "\t<key id=\"edge_color\" for=\"edge\" attr.name=\"color\" attr.type=\"string\"/>"
+    )?;
+
+    writeln!(
+        output,
+        "\t<graph id=\"dependencies\" edgedefault=\"directed\">"
+    )?;
+
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        writeln!(output, "\t\t<node id=\"n{}\">", i)?;
+        let data = |key: &str, value: &str| {
+            format!("\t\t\t<data key=\"{}\">{}</data>", key, xml_escape(value))
+        };
+        writeln!(output, "{}", data("name", &dep.name))?;
+        writeln!(output, "{}", data("version", &dep.ver))?;
+        writeln!(output, "{}", data("kind", &format!("{:?}", dep.kind())))?;
+        writeln!(output, "{}", data("color", dep.kind().color()))?;
+        writeln!(output, "{}", data("regular", &dep.is_regular.to_string()))?;
+        writeln!(output, "{}", data("build", &dep.is_build.to_string()))?;
+        writeln!(output, "{}", data("dev", &dep.is_dev.to_string()))?;
+        writeln!(output, "{}", data("optional", &dep.is_optional.to_string()))?;
+        writeln!(output, "{}", data("is_root", &(i == 0).to_string()))?;
+        if let Some(source) = &dep.source {
+            writeln!(output, "{}", data("source", source))?;
+        }
+        if let Some(license) = &dep.license {
+            writeln!(output, "{}", data("license", license))?;
+        }
+        if let Some(depth) = dep.depth {
+            writeln!(output, "{}", data("depth", &depth.to_string()))?;
+        }
+        writeln!(output, "\t\t</node>")?;
+    }
+
+    for (i, ed) in dg.edges.iter().enumerate() {
+        let kind = ed.kind(dg, root_deps_map);
+        writeln!(
+            output,
+            "\t\t<edge id=\"e{}\" source=\"n{}\" target=\"n{}\">",
+            i, ed.0, ed.1
+        )?;
+        writeln!(output, "\t\t\t<data key=\"edge_kind\">{:?}</data>", kind)?;
+        writeln!(
+            output,
+            "\t\t\t<data key=\"edge_color\">{}</data>",
+            kind.color()
+        )?;
+        writeln!(output, "\t\t</edge>")?;
+    }
+
+    writeln!(output, "\t</graph>")?;
+    writeln!(output, "</graphml>")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dep::DepKind::{Build, Dev, Regular};
+    use std::collections::HashMap;
+
+    #[test]
+    fn keys_and_data() {
+        let mut dg = DepGraph::new(Config::from_args(&[]));
+        let root = dg.find_or_add("root", "1.0.0");
+        let serde = dg.add_child(root, "serde", "1.0.0");
+        dg.add_child(root, "cc", "1.0.0");
+        dg.add_child(root, "criterion", "0.3.0");
+        dg.nodes[serde].source = Some("registry+https://example.com/?a&b".into());
+        dg.nodes[serde].license = Some("MIT/Apache-2.0".into());
+        dg.nodes[serde].depth = Some(1);
+        let mut declared = HashMap::new();
+        declared.insert("serde".to_string(), vec![Regular]);
+        declared.insert("cc".to_string(), vec![Build]);
+        declared.insert("criterion".to_string(), vec![Dev]);
+        dg.set_resolved_kind(&declared);
+
+        let mut output = vec![];
+        render(&dg, &mut output, &declared).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(output.matches("<key id=").count(), NODE_KEYS.len() + 2);
+        for expected in [
+            "\t<key id=\"regular\" for=\"node\" attr.name=\"regular\" attr.type=\"boolean\"/>\n",
+            "\t<key id=\"depth\" for=\"node\" attr.name=\"depth\" attr.type=\"int\"/>\n",
+            "\t<key id=\"edge_kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+            "\t\t<node id=\"n1\">\n\
+             \t\t\t<data key=\"name\">serde</data>\n\
+             \t\t\t<data key=\"version\">1.0.0</data>\n\
+             \t\t\t<data key=\"kind\">Regular</data>\n\
+             \t\t\t<data key=\"color\">black</data>\n\
+             \t\t\t<data key=\"regular\">true</data>\n\
+             \t\t\t<data key=\"build\">false</data>\n\
+             \t\t\t<data key=\"dev\">false</data>\n\
+             \t\t\t<data key=\"optional\">false</data>\n\
+             \t\t\t<data key=\"is_root\">false</data>\n\
+             \t\t\t<data key=\"source\">registry+https://example.com/?a&amp;b</data>\n\
+             \t\t\t<data key=\"license\">MIT/Apache-2.0</data>\n\
+             \t\t\t<data key=\"depth\">1</data>\n\
+             \t\t</node>\n",
+            "\t\t<edge id=\"e1\" source=\"n0\" target=\"n2\">\n\
+             \t\t\t<data key=\"edge_kind\">Build</data>\n\
+             \t\t\t<data key=\"edge_color\">purple</data>\n",
+            "\t\t<edge id=\"e2\" source=\"n0\" target=\"n3\">\n\
+             \t\t\t<data key=\"edge_kind\">Dev</data>\n\
+             \t\t\t<data key=\"edge_color\">blue</data>\n",
+        ] {
+            assert!(
+                output.contains(expected),
+                "{}\nnot in:\n{}",
+                expected,
+                output
+            );
+        }
+        assert!(output.contains("\t\t\t<data key=\"is_root\">true</data>\n"));
+        assert!(output.ends_with("\t</graph>\n</graphml>\n"));
+    }
+}
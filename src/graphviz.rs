@@ -0,0 +1,124 @@
+use crate::error::{CliError, CliResult};
+use crate::graph::DepGraph;
+use crate::project::DeclaredDepsMap;
+use std::ffi::OsString;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// The layout engines of Graphviz that can be chosen with `--engine`.
+pub const ENGINES: &[&str] = &["dot", "neato", "sfdp", "fdp"];
+
+/// The output file extensions that are rendered by Graphviz.
+const IMAGE_TYPES: &[&str] = &["png", "svg", "pdf", "jpg", "jpeg", "gif"];
+
+/// Returns the Graphviz output type for the extension of `path`, if it is an image.
+pub fn image_type(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    if IMAGE_TYPES.contains(&&*ext) {
+        Some(ext)
+    } else {
+        None
+    }
+}
+
+/// Renders the graph to the image file `path` by piping the DOT output to Graphviz's `dot`.
+pub fn render_image(
+    graph: DepGraph,
+    root_deps_map: &DeclaredDepsMap,
+    path: &Path,
+    image_type: &str,
+) -> CliResult<()> {
+    let mut args: Vec<OsString> = vec![format!("-T{}", image_type).into(), "-o".into()];
+    args.push(path.into());
+    if let Some(engine) = &graph.cfg.engine {
+        args.push(format!("-K{}", engine).into());
+    }
+
+    let mut dot = Vec::new();
+    graph.render_to(&mut dot, root_deps_map)?;
+
+    run_dot("dot", &args, dot, path)
+}
+
+/// Runs `program` with `args`, writing `input` to its stdin, and reports its stderr if it fails.
+fn run_dot(program: &str, args: &[OsString], input: Vec<u8>, path: &Path) -> CliResult<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => CliError::Generic(format!(
+                "Could not find Graphviz's `dot` to render {}. Install Graphviz from \
+                 https://graphviz.org/download/, or write the DOT output to a .dot file",
+                path.display()
+            )),
+            _ => CliError::Generic(format!("Could not run `dot`: {}", e)),
+        })?;
+
+    // Write on another thread while `wait_with_output` drains stderr, so that neither side blocks
+    // when `dot` prints many warnings, and so that its errors are reported when it exits early.
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output()?;
+    let written = writer.join().expect("the thread writing to `dot` panicked");
+    if !output.status.success() {
+        return Err(CliError::Generic(format!(
+            "`dot` failed to render {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    written?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_type_is_the_lowercase_extension_of_images() {
+        assert_eq!(image_type(Path::new("deps.png")), Some("png".into()));
+        assert_eq!(image_type(Path::new("out/deps.SVG")), Some("svg".into()));
+        assert_eq!(image_type(Path::new("deps.jpeg")), Some("jpeg".into()));
+        assert_eq!(image_type(Path::new("deps.dot")), None);
+        assert_eq!(image_type(Path::new("deps")), None);
+    }
+
+    #[test]
+    fn missing_dot_suggests_installing_graphviz() {
+        let err = run_dot(
+            "cargo-deps-missing-dot",
+            &[],
+            Vec::new(),
+            Path::new("deps.png"),
+        )
+        .unwrap_err();
+
+        let msg = err.to_string();
+        assert!(msg.starts_with("Could not find Graphviz's `dot` to render deps.png."));
+        assert!(msg.contains("https://graphviz.org/download/"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn early_exit_reports_stderr_instead_of_a_broken_pipe() {
+        // Exits without reading its input, like `dot` does on a syntax error.
+        let args = [
+            "-c".into(),
+            "echo 'syntax error in line 1' >&2; exit 1".into(),
+        ];
+        let input = vec![b'x'; 1 << 20];
+        let err = run_dot("sh", &args, input, Path::new("deps.png")).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "`dot` failed to render deps.png: syntax error in line 1"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::dep::DepKind;
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use crate::project::DeclaredDepsMap;
+use crate::util::json_escape;
+use std::io::Write;
+
+/// The viewer page. The graph data replaces the `GRAPH_DATA` placeholder and the name of the root
+/// crate replaces the `TITLE` placeholder.
+const TEMPLATE: &str = include_str!("viewer.html");
+
+const KINDS: [DepKind; 5] = [
+    DepKind::Regular,
+    DepKind::Build,
+    DepKind::Dev,
+    DepKind::Optional,
+    DepKind::Unknown,
+];
+
+/// Writes a standalone HTML page for exploring the graph. The graph is embedded in the page, which
+/// lays it out by itself, so it can be opened without any network access.
+pub fn render<W: Write>(
+    dg: &DepGraph,
+    output: &mut W,
+    root_deps_map: &DeclaredDepsMap,
+) -> CliResult<()> {
+    let mut data = String::from("{\"nodes\":[");
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        if i > 0 {
+            data.push(',');
+        }
+        data.push_str(&format!(
+            "{{\"name\":\"{}\",\"version\":\"{}\",\"label\":\"{}\",\"kind\":\"{:?}\"}}",
+            json_escape(&dep.name),
+            json_escape(&dep.ver),
+            json_escape(&dep.display_name(&dg.cfg)),
+            dep.kind()
+        ));
+    }
+
+    data.push_str("],\"edges\":[");
+    for (i, ed) in dg.edges.iter().enumerate() {
+        if i > 0 {
+            data.push(',');
+        }
+        data.push_str(&format!(
+            "{{\"from\":{},\"to\":{},\"kind\":\"{:?}\"}}",
+            ed.0,
+            ed.1,
+            ed.kind(dg, root_deps_map)
+        ));
+    }
+
+    data.push_str("],\"colors\":{");
+    for (i, kind) in KINDS.iter().enumerate() {
+        if i > 0 {
+            data.push(',');
+        }
+        data.push_str(&format!("\"{:?}\":\"{}\"", kind, kind.color()));
+    }
+    data.push_str("}}");
+
+    let title = dg.nodes.first().map_or("", |root| &root.name);
+    let (head, tail) = TEMPLATE.split_at(TEMPLATE.find("GRAPH_DATA").unwrap());
+    let head = head.replacen(
+        "TITLE",
+        &title.replace('&', "&amp;").replace('<', "&lt;"),
+        1,
+    );
+    write!(output, "{}{}{}", head, data, &tail["GRAPH_DATA".len()..])?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dep::DepKind::{Build, Regular};
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    const TRICKY: &str = "a</script><script>alert(\"\\\")</script>";
+
+    #[test]
+    fn data_is_embedded_as_json() {
+        let mut dg = DepGraph::new(Config::from_args(&[]));
+        let root = dg.find_or_add(TRICKY, "1.0.0");
+        dg.add_child(root, "serde", "1.0.0");
+        dg.add_child(root, "cc", "1.0.0");
+        let mut declared = HashMap::new();
+        declared.insert("serde".to_string(), vec![Regular]);
+        declared.insert("cc".to_string(), vec![Build]);
+        dg.set_resolved_kind(&declared);
+
+        let mut output = vec![];
+        render(&dg, &mut output, &declared).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        // The names can't close the script or the title early.
+        assert_eq!(output.matches("</script>").count(), 1);
+        assert!(output.contains(
+            "<title>a&lt;/script>&lt;script>alert(\"\\\")&lt;/script> dependencies</title>"
+        ));
+
+        let line = output
+            .lines()
+            .find(|line| line.starts_with("var GRAPH = "))
+            .unwrap();
+        let data: Value = serde_json::from_str(
+            line.trim_start_matches("var GRAPH = ")
+                .trim_end_matches(';'),
+        )
+        .unwrap();
+
+        assert_eq!(data["nodes"][0]["name"], TRICKY);
+        assert_eq!(data["nodes"][0]["label"], TRICKY);
+        assert_eq!(data["nodes"][1]["name"], "serde");
+        assert_eq!(data["nodes"][1]["kind"], "Regular");
+        assert_eq!(data["nodes"][2]["kind"], "Build");
+        assert_eq!(data["edges"][1]["from"], 0);
+        assert_eq!(data["edges"][1]["to"], 2);
+        assert_eq!(data["edges"][1]["kind"], "Build");
+        assert_eq!(data["colors"]["Build"], Build.color());
+    }
+}
@@ -0,0 +1,342 @@
+use crate::dep::ResolvedDep;
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use crate::manifest::cargo_home;
+use semver::{Version, VersionReq};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Reads the published versions of crates from a local copy of the crates.io index, without
+/// accessing the network.
+#[derive(Debug)]
+pub struct CrateIndex {
+    dirs: Vec<PathBuf>,
+}
+
+impl CrateIndex {
+    /// Uses the index checkout at `path` if given. Otherwise, uses the index caches of cargo under
+    /// `~/.cargo/registry/index`, which are kept up to date by cargo for both git and sparse
+    /// registries.
+    pub fn new(path: Option<&str>) -> Self {
+        let dirs = match path {
+            Some(path) => vec![PathBuf::from(path)],
+            None => match cargo_home().map(|home| home.join("registry").join("index")) {
+                Some(index) => match fs::read_dir(index) {
+                    Ok(entries) => entries
+                        .filter_map(|e| e.ok())
+                        .map(|e| e.path())
+                        .filter(|p| p.is_dir())
+                        .collect(),
+                    Err(_) => vec![],
+                },
+                None => vec![],
+            },
+        };
+
+        CrateIndex { dirs }
+    }
+
+    /// Returns the versions of `name` that were published and not yanked. If several indexes have
+    /// the crate, e.g. the caches of both the git and the sparse registry, the file modified last
+    /// is read, as the others may be stale.
+    pub fn versions(&self, name: &str) -> Vec<Version> {
+        let rel_path = index_path(&name.to_lowercase());
+
+        let newest = self
+            .dirs
+            .iter()
+            .flat_map(|dir| vec![dir.join(&rel_path), dir.join(".cache").join(&rel_path)])
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((modified, path))
+            })
+            .max_by_key(|(modified, _)| *modified);
+
+        match newest.and_then(|(_, path)| fs::read(path).ok()) {
+            Some(content) => parse_entries(&content),
+            None => vec![],
+        }
+    }
+
+    /// Sets the newer releases of every registry dependency of the graph.
+    pub fn annotate(&self, dg: &mut DepGraph) {
+        for dep in dg.nodes.iter_mut() {
+            let is_registry = dep.source.as_ref().is_some_and(|source| {
+                source.starts_with("registry+") || source.starts_with("sparse+")
+            });
+            if !is_registry {
+                continue;
+            }
+
+            let (compatible, latest) = newer_versions(dep, &self.versions(&dep.name));
+            dep.newer_compatible = compatible;
+            dep.newer_incompatible = latest;
+        }
+    }
+}
+
+/// The path of the file of a crate in the index, e.g. `se/rd/serde` or `3/l/log`.
+fn index_path(name: &str) -> PathBuf {
+    match name.len() {
+        1 => Path::new("1").join(name),
+        2 => Path::new("2").join(name),
+        3 => Path::new("3").join(&name[..1]).join(name),
+        _ => Path::new(&name[..2]).join(&name[2..4]).join(name),
+    }
+}
+
+/// Parses the entries of an index file. Each version is described by a JSON object, on its own
+/// line in index checkouts, and separated by NUL bytes in the cache files of cargo.
+fn parse_entries(content: &[u8]) -> Vec<Version> {
+    let content = String::from_utf8_lossy(content);
+
+    content
+        .split(['\n', '\0'])
+        .filter_map(|entry| serde_json::from_str::<serde_json::Value>(entry).ok())
+        .filter(|entry| entry["yanked"] != true)
+        .filter_map(|entry| Version::parse(entry["vers"].as_str()?).ok())
+        .collect()
+}
+
+/// Returns the newest semver-compatible version newer than the version of `dep`, and the newest
+/// version if it isn't compatible. Pre-releases are only considered for dependencies that are
+/// pre-releases themselves.
+fn newer_versions(dep: &ResolvedDep, versions: &[Version]) -> (Option<Version>, Option<Version>) {
+    let current = match &dep.version {
+        Some(current) => current,
+        None => return (None, None),
+    };
+    let compatible = match VersionReq::parse(&format!("^{}", current)) {
+        Ok(req) => req,
+        Err(_) => return (None, None),
+    };
+
+    let newer = versions
+        .iter()
+        .filter(|v| *v > current && (v.pre.is_empty() || !current.pre.is_empty()));
+    let newest_compatible = newer.clone().filter(|v| compatible.matches(v)).max();
+    let newest = newer.filter(|v| !compatible.matches(v)).max();
+
+    (newest_compatible.cloned(), newest.cloned())
+}
+
+/// Writes a table of the dependencies that have newer releases in the index, with the newest
+/// semver-compatible release and the newest release overall.
+pub fn render_outdated<W: Write>(dg: &DepGraph, output: &mut W) -> CliResult<()> {
+    let mut rows = dg
+        .nodes
+        .iter()
+        .filter(|dep| dep.is_outdated())
+        .map(|dep| {
+            let version = |v: &Option<Version>| v.as_ref().map_or("-".into(), |v| v.to_string());
+            (
+                dep.name.clone(),
+                dep.ver.clone(),
+                version(&dep.newer_compatible),
+                version(
+                    &dep.newer_incompatible
+                        .clone()
+                        .or(dep.newer_compatible.clone()),
+                ),
+            )
+        })
+        .collect::<Vec<_>>();
+    rows.sort();
+
+    let width = |column: fn(&(String, String, String, String)) -> &String, title: &str| {
+        rows.iter()
+            .map(|row| column(row).len())
+            .max()
+            .unwrap_or(0)
+            .max(title.len())
+    };
+    let name_width = width(|row| &row.0, "NAME");
+    let ver_width = width(|row| &row.1, "VERSION");
+    let compat_width = width(|row| &row.2, "COMPATIBLE");
+
+    writeln!(
+        output,
+        "{:nw$}  {:vw$}  {:cw$}  LATEST",
+        "NAME",
+        "VERSION",
+        "COMPATIBLE",
+        nw = name_width,
+        vw = ver_width,
+        cw = compat_width
+    )?;
+    for (name, ver, compatible, latest) in rows {
+        writeln!(
+            output,
+            "{:nw$}  {:vw$}  {:cw$}  {}",
+            name,
+            ver,
+            compatible,
+            latest,
+            nw = name_width,
+            vw = ver_width,
+            cw = compat_width
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::time::{Duration, SystemTime};
+
+    fn entry(vers: &str, yanked: bool) -> String {
+        format!(
+            "{{\"name\":\"rand\",\"vers\":\"{}\",\"deps\":[],\"cksum\":\"00\",\"features\":{{}},\
+             \"yanked\":{}}}",
+            vers, yanked
+        )
+    }
+
+    /// A cache file of cargo: a header with the cache and index versions and the index revision,
+    /// then a NUL-terminated version and JSON entry for each release.
+    fn cache_file(entries: &[(&str, bool)]) -> Vec<u8> {
+        let mut content = vec![3, 2, 0, 0, 0];
+        content.extend_from_slice(b"etag: \"abc\"\0");
+        for &(vers, yanked) in entries {
+            content.extend_from_slice(vers.as_bytes());
+            content.push(0);
+            content.extend_from_slice(entry(vers, yanked).as_bytes());
+            content.push(0);
+        }
+        content
+    }
+
+    fn versions(versions: &[&str]) -> Vec<Version> {
+        versions
+            .iter()
+            .map(|v| Version::parse(v).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn index_paths_depend_on_the_name_length() {
+        assert_eq!(index_path("a"), Path::new("1/a"));
+        assert_eq!(index_path("cc"), Path::new("2/cc"));
+        assert_eq!(index_path("log"), Path::new("3/l/log"));
+        assert_eq!(index_path("serde"), Path::new("se/rd/serde"));
+        assert_eq!(index_path("rand"), Path::new("ra/nd/rand"));
+    }
+
+    #[test]
+    fn checkout_entries_are_parsed_without_yanked_versions() {
+        let content = [
+            entry("0.5.0", false),
+            entry("0.6.0", true),
+            entry("0.6.1", false),
+            String::new(),
+        ]
+        .join("\n");
+        assert_eq!(
+            parse_entries(content.as_bytes()),
+            versions(&["0.5.0", "0.6.1"])
+        );
+    }
+
+    #[test]
+    fn entries_are_parsed_regardless_of_whitespace_and_nesting() {
+        let content = "{ \"name\": \"rand\", \"vers\": \"0.5.0\", \"yanked\": true }\n\
+                       {\"name\": \"rand\",\t\"deps\": [{\"name\": \"vers\", \"features\": \
+                       [\"\\\"vers\\\":\\\"9.9.9\\\"\"]}], \"vers\" : \"0.6.0\", \
+                       \"yanked\" : false}\n";
+        assert_eq!(parse_entries(content.as_bytes()), versions(&["0.6.0"]));
+    }
+
+    #[test]
+    fn cache_entries_are_parsed_without_yanked_versions() {
+        let content = cache_file(&[("0.5.0", false), ("0.6.0", true), ("0.7.0-beta.1", false)]);
+        assert_eq!(
+            parse_entries(&content),
+            versions(&["0.5.0", "0.7.0-beta.1"])
+        );
+    }
+
+    #[test]
+    fn versions_are_read_from_checkouts_and_caches() {
+        let dir = env::temp_dir().join(format!("cargo-deps-index-{}", std::process::id()));
+        let checkout = dir.join("checkout");
+        let cache = dir.join("cache");
+        fs::create_dir_all(checkout.join("ra/nd")).unwrap();
+        fs::create_dir_all(cache.join(".cache/3/l")).unwrap();
+        fs::write(checkout.join("ra/nd/rand"), entry("0.6.0", false)).unwrap();
+        fs::write(
+            cache.join(".cache/3/l/log"),
+            cache_file(&[("0.4.1", false)]),
+        )
+        .unwrap();
+
+        let index = CrateIndex {
+            dirs: vec![checkout, cache],
+        };
+        let result = (
+            index.versions("Rand"),
+            index.versions("log"),
+            index.versions("serde"),
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.0, versions(&["0.6.0"]));
+        assert_eq!(result.1, versions(&["0.4.1"]));
+        assert_eq!(result.2, vec![]);
+    }
+
+    #[test]
+    fn the_most_recent_copy_of_a_crate_is_read() {
+        let dir = env::temp_dir().join(format!("cargo-deps-index-stale-{}", std::process::id()));
+        let (stale, fresh) = (dir.join("stale"), dir.join("fresh"));
+        for (index, vers) in [(&fresh, "0.7.0"), (&stale, "0.6.0")] {
+            fs::create_dir_all(index.join(".cache/ra/nd")).unwrap();
+            fs::write(
+                index.join(".cache/ra/nd/rand"),
+                cache_file(&[(vers, false)]),
+            )
+            .unwrap();
+        }
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(stale.join(".cache/ra/nd/rand"))
+            .and_then(|file| file.set_modified(old))
+            .unwrap();
+
+        let index = CrateIndex {
+            dirs: vec![stale, fresh],
+        };
+        let result = index.versions("rand");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result, versions(&["0.7.0"]));
+    }
+
+    #[test]
+    fn newer_versions_are_split_by_compatibility() {
+        let published = versions(&["0.6.0", "0.6.5", "0.7.0", "0.8.0-alpha", "0.5.0"]);
+        let dep = ResolvedDep::new("rand".into(), "0.6.1".into());
+        assert_eq!(
+            newer_versions(&dep, &published),
+            (Some(Version::new(0, 6, 5)), Some(Version::new(0, 7, 0)))
+        );
+
+        let latest = ResolvedDep::new("rand".into(), "0.7.0".into());
+        assert_eq!(newer_versions(&latest, &published), (None, None));
+
+        let pre = ResolvedDep::new("rand".into(), "0.7.0".into());
+        let published = versions(&["0.7.1-rc.1"]);
+        assert_eq!(newer_versions(&pre, &published), (None, None));
+
+        let pre = ResolvedDep::new("rand".into(), "0.8.0-alpha".into());
+        let published = versions(&["0.8.0-beta", "0.8.0"]);
+        assert_eq!(
+            newer_versions(&pre, &published),
+            (Some(Version::new(0, 8, 0)), None)
+        );
+    }
+}
@@ -0,0 +1,546 @@
+const LAYER_GAP: f64 = 60.0;
+const NODE_GAP: f64 = 24.0;
+const DUMMY_WIDTH: f64 = 8.0;
+const MARGIN: f64 = 20.0;
+const CLUSTER_PADDING: f64 = 12.0;
+const CLUSTER_LABEL_HEIGHT: f64 = 20.0;
+const ORDER_ITERATIONS: usize = 12;
+const PLACE_ITERATIONS: usize = 8;
+
+/// The position of a node, given by its center and its size.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug)]
+pub struct Layout {
+    pub width: f64,
+    pub height: f64,
+    pub nodes: Vec<Rect>,
+    /// The points of each edge, from its source to its target.
+    pub edges: Vec<Vec<(f64, f64)>>,
+    /// The box around the nodes of the cluster, with room for a label at its top, if the cluster
+    /// isn't empty.
+    pub cluster: Option<Rect>,
+}
+
+/// Lays out a graph whose nodes have the given (width, height) sizes, in layers from top to bottom.
+/// This follows the method of Sugiyama et al.: cycles are broken, nodes are assigned to layers,
+/// long edges are split by dummy nodes, the nodes of each layer are ordered to reduce crossings,
+/// and finally given coordinates.
+///
+/// The nodes for which `in_cluster` is true are kept together at the right of their layers, apart
+/// from the other nodes, so that a box can be drawn around them.
+pub fn layout(sizes: &[(f64, f64)], edges: &[(usize, usize)], in_cluster: &[bool]) -> Layout {
+    let n = sizes.len();
+    let row_height = sizes.iter().map(|s| s.1).fold(0.0, f64::max);
+
+    // Reverse the edges that close cycles, so that the graph becomes acyclic.
+    let reversed = back_edges(n, edges);
+    let dag = edges
+        .iter()
+        .zip(&reversed)
+        .map(|(&(a, b), &rev)| if rev { (b, a) } else { (a, b) })
+        .collect::<Vec<_>>();
+    let layer = longest_path_layers(n, &dag);
+
+    // Split the edges spanning several layers with dummy nodes. The chain of each edge goes from
+    // its upper end to its lower end.
+    let mut vlayer = layer.clone();
+    let mut vwidth = sizes.iter().map(|s| s.0).collect::<Vec<_>>();
+    let mut vcluster = in_cluster.to_vec();
+    let mut segments = vec![];
+    let mut chains = vec![];
+    for &(a, b) in &dag {
+        let mut chain = vec![a];
+        let mut prev = a;
+        for l in layer[a] + 1..layer[b] {
+            let dummy = vlayer.len();
+            vlayer.push(l);
+            vwidth.push(DUMMY_WIDTH);
+            // The edges between two nodes of the cluster stay inside it.
+            vcluster.push(in_cluster[a] && in_cluster[b]);
+            segments.push((prev, dummy));
+            chain.push(dummy);
+            prev = dummy;
+        }
+        segments.push((prev, b));
+        chain.push(b);
+        chains.push(chain);
+    }
+
+    let vn = vlayer.len();
+    let mut ups = vec![vec![]; vn];
+    let mut downs = vec![vec![]; vn];
+    for &(a, b) in &segments {
+        downs[a].push(b);
+        ups[b].push(a);
+    }
+
+    let mut layers = initial_order(&vlayer, &downs);
+    let mut pos = positions(&layers, vn);
+    let mut best = (crossings(&layers, &downs, &pos), layers.clone());
+    for iteration in 0..ORDER_ITERATIONS {
+        if iteration % 2 == 0 {
+            for nodes in layers.iter_mut().skip(1) {
+                sort_by_barycenter(nodes, &ups, &mut pos);
+            }
+        } else {
+            for nodes in layers.iter_mut().rev().skip(1) {
+                sort_by_barycenter(nodes, &downs, &mut pos);
+            }
+        }
+
+        let count = crossings(&layers, &downs, &pos);
+        if count < best.0 {
+            best = (count, layers.clone());
+        }
+    }
+    let mut layers = best.1;
+    for nodes in &mut layers {
+        nodes.sort_by_key(|&v| vcluster[v]);
+    }
+
+    // Pack each layer from the left, then repeatedly move the nodes towards their neighbors.
+    let mut x = vec![0.0; vn];
+    for nodes in &layers {
+        let mut right = 0.0;
+        for &v in nodes {
+            x[v] = right + vwidth[v] / 2.0;
+            right += vwidth[v] + NODE_GAP;
+        }
+    }
+    for iteration in 0..PLACE_ITERATIONS {
+        let neighbors = if iteration % 2 == 0 { &ups } else { &downs };
+        for nodes in &layers {
+            let desired = nodes
+                .iter()
+                .map(|&v| match neighbors[v].len() {
+                    0 => x[v],
+                    len => neighbors[v].iter().map(|&u| x[u]).sum::<f64>() / len as f64,
+                })
+                .collect::<Vec<_>>();
+            let widths = nodes.iter().map(|&v| vwidth[v]).collect::<Vec<_>>();
+            for (&v, new_x) in nodes.iter().zip(place_layer(&desired, &widths)) {
+                x[v] = new_x;
+            }
+        }
+    }
+
+    // Move the cluster to the right of the other nodes of the layers it spans.
+    let members = (0..vn).filter(|&v| vcluster[v]).collect::<Vec<_>>();
+    let cluster_layers = members.iter().map(|&v| vlayer[v]);
+    let (first_layer, last_layer) = (
+        cluster_layers.clone().min().unwrap_or(0),
+        cluster_layers.max().unwrap_or(0),
+    );
+    let others_right = (0..vn)
+        .filter(|&v| !vcluster[v] && (first_layer..=last_layer).contains(&vlayer[v]))
+        .map(|v| x[v] + vwidth[v] / 2.0)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let cluster_left = members
+        .iter()
+        .map(|&v| x[v] - vwidth[v] / 2.0)
+        .fold(f64::INFINITY, f64::min);
+    let overlap = others_right + NODE_GAP + 2.0 * CLUSTER_PADDING - cluster_left;
+    if overlap > 0.0 {
+        for &v in &members {
+            x[v] += overlap;
+        }
+    }
+    let cluster_box = |x: &[f64]| {
+        let left = members
+            .iter()
+            .map(|&v| x[v] - vwidth[v] / 2.0)
+            .fold(f64::INFINITY, f64::min);
+        let right = members
+            .iter()
+            .map(|&v| x[v] + vwidth[v] / 2.0)
+            .fold(f64::NEG_INFINITY, f64::max);
+        (left - CLUSTER_PADDING, right + CLUSTER_PADDING)
+    };
+
+    let mut left = (0..vn)
+        .map(|v| x[v] - vwidth[v] / 2.0)
+        .fold(f64::INFINITY, f64::min);
+    if !members.is_empty() {
+        left = left.min(cluster_box(&x).0);
+    }
+    let shift = if left.is_finite() { MARGIN - left } else { 0.0 };
+    for x in &mut x {
+        *x += shift;
+    }
+    // Leave room above the first layer for the top of the cluster and its label.
+    let top = if members.is_empty() {
+        MARGIN
+    } else {
+        MARGIN + CLUSTER_PADDING + CLUSTER_LABEL_HEIGHT
+    };
+    let y = |l: usize| top + row_height / 2.0 + l as f64 * (row_height + LAYER_GAP);
+
+    let cluster = if members.is_empty() {
+        None
+    } else {
+        let (left, right) = cluster_box(&x);
+        let upper = y(first_layer) - row_height / 2.0 - CLUSTER_PADDING - CLUSTER_LABEL_HEIGHT;
+        let lower = y(last_layer) + row_height / 2.0 + CLUSTER_PADDING;
+        Some(Rect {
+            x: (left + right) / 2.0,
+            y: (upper + lower) / 2.0,
+            width: right - left,
+            height: lower - upper,
+        })
+    };
+
+    let nodes = (0..n)
+        .map(|v| Rect {
+            x: x[v],
+            y: y(layer[v]),
+            width: sizes[v].0,
+            height: sizes[v].1,
+        })
+        .collect::<Vec<_>>();
+
+    let edges = chains
+        .iter()
+        .zip(&reversed)
+        .map(|(chain, &rev)| {
+            let mut points = chain
+                .iter()
+                .map(|&v| (x[v], y(vlayer[v])))
+                .collect::<Vec<_>>();
+            // Attach the ends of the edge to the bottom of the upper node and the top of the lower
+            // node.
+            let first = chain[0];
+            let last = chain[chain.len() - 1];
+            points[0].1 += sizes[first].1 / 2.0;
+            let end = points.len() - 1;
+            points[end].1 -= sizes[last].1 / 2.0;
+            if rev {
+                points.reverse();
+            }
+            points
+        })
+        .collect::<Vec<_>>();
+
+    let width = (0..vn)
+        .map(|v| x[v] + vwidth[v] / 2.0)
+        .chain(cluster.map(|c| c.x + c.width / 2.0))
+        .fold(0.0, f64::max)
+        + MARGIN;
+    let height = match layers.len() {
+        0 => 0.0,
+        len => y(len - 1) + row_height / 2.0 + MARGIN,
+    };
+
+    Layout {
+        width,
+        height,
+        nodes,
+        edges,
+        cluster,
+    }
+}
+
+/// Returns whether each edge closes a cycle, found with a depth-first search from node 0 and then
+/// from the other unvisited nodes.
+fn back_edges(n: usize, edges: &[(usize, usize)]) -> Vec<bool> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        New,
+        Active,
+        Done,
+    }
+
+    let mut out = vec![vec![]; n];
+    for (i, &(a, _)) in edges.iter().enumerate() {
+        out[a].push(i);
+    }
+
+    let mut state = vec![State::New; n];
+    let mut back = vec![false; edges.len()];
+    for start in 0..n {
+        if state[start] != State::New {
+            continue;
+        }
+        state[start] = State::Active;
+        let mut stack = vec![(start, 0)];
+        while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+            if let Some(&e) = out[node].get(*next) {
+                *next += 1;
+                let child = edges[e].1;
+                match state[child] {
+                    State::New => {
+                        state[child] = State::Active;
+                        stack.push((child, 0));
+                    }
+                    State::Active => back[e] = true,
+                    State::Done => (),
+                }
+            } else {
+                state[node] = State::Done;
+                stack.pop();
+            }
+        }
+    }
+
+    back
+}
+
+/// Places each node one layer below its lowest parent. `edges` must be acyclic.
+fn longest_path_layers(n: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut out = vec![vec![]; n];
+    let mut in_degree = vec![0; n];
+    for &(a, b) in edges {
+        out[a].push(b);
+        in_degree[b] += 1;
+    }
+
+    let mut layer = vec![0; n];
+    let mut queue = (0..n).filter(|&v| in_degree[v] == 0).collect::<Vec<_>>();
+    while let Some(v) = queue.pop() {
+        for &child in &out[v] {
+            layer[child] = layer[child].max(layer[v] + 1);
+            in_degree[child] -= 1;
+            if in_degree[child] == 0 {
+                queue.push(child);
+            }
+        }
+    }
+
+    layer
+}
+
+/// Orders the nodes of each layer by a depth-first traversal from node 0, so that the children
+/// of a node start out close to each other.
+fn initial_order(vlayer: &[usize], downs: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let layer_count = vlayer.iter().max().map_or(0, |&l| l + 1);
+    let mut layers = vec![vec![]; layer_count];
+    let mut visited = vec![false; vlayer.len()];
+
+    for start in 0..vlayer.len() {
+        let mut stack = vec![start];
+        while let Some(v) = stack.pop() {
+            if visited[v] {
+                continue;
+            }
+            visited[v] = true;
+            layers[vlayer[v]].push(v);
+            stack.extend(downs[v].iter().rev());
+        }
+    }
+
+    layers
+}
+
+fn positions(layers: &[Vec<usize>], vn: usize) -> Vec<usize> {
+    let mut pos = vec![0; vn];
+    for nodes in layers {
+        for (i, &v) in nodes.iter().enumerate() {
+            pos[v] = i;
+        }
+    }
+    pos
+}
+
+/// Sorts the nodes of a layer by the mean position of their neighbors in the adjacent layer. Nodes
+/// without neighbors keep their position.
+fn sort_by_barycenter(nodes: &mut [usize], neighbors: &[Vec<usize>], pos: &mut [usize]) {
+    let mut keyed = nodes
+        .iter()
+        .map(|&v| {
+            let key = match neighbors[v].len() {
+                0 => pos[v] as f64,
+                len => neighbors[v].iter().map(|&u| pos[u] as f64).sum::<f64>() / len as f64,
+            };
+            (key, v)
+        })
+        .collect::<Vec<_>>();
+    keyed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    for (i, (_, v)) in keyed.into_iter().enumerate() {
+        nodes[i] = v;
+        pos[v] = i;
+    }
+}
+
+/// Counts the crossings between each pair of adjacent layers.
+fn crossings(layers: &[Vec<usize>], downs: &[Vec<usize>], pos: &[usize]) -> usize {
+    let mut count = 0;
+    for nodes in layers {
+        let mut ends = nodes
+            .iter()
+            .flat_map(|&u| downs[u].iter().map(move |&v| (pos[u], pos[v])))
+            .collect::<Vec<_>>();
+        ends.sort();
+
+        // Two segments cross if their upper ends are in one order and their lower ends in the
+        // other, so count the inversions of the lower ends.
+        let size = ends.iter().map(|e| e.1 + 1).max().unwrap_or(0);
+        let mut tree = vec![0; size + 1];
+        for (seen, &(_, lower)) in ends.iter().enumerate() {
+            let mut not_greater = 0;
+            let mut i = lower + 1;
+            while i > 0 {
+                not_greater += tree[i];
+                i &= i - 1;
+            }
+            count += seen - not_greater;
+
+            let mut i = lower + 1;
+            while i <= size {
+                tree[i] += 1;
+                i += i & i.wrapping_neg();
+            }
+        }
+    }
+    count
+}
+
+/// Places the nodes of a layer as close as possible to their desired centers, in order and
+/// without overlapping. This averages a placement pushed from the left with one pushed from the
+/// right, which both keep the nodes apart.
+fn place_layer(desired: &[f64], widths: &[f64]) -> Vec<f64> {
+    let len = desired.len();
+    let gap = |i: usize| (widths[i - 1] + widths[i]) / 2.0 + NODE_GAP;
+
+    let mut from_left = desired.to_vec();
+    for i in 1..len {
+        from_left[i] = from_left[i].max(from_left[i - 1] + gap(i));
+    }
+    let mut from_right = desired.to_vec();
+    for i in (1..len).rev() {
+        from_right[i - 1] = from_right[i - 1].min(from_right[i] - gap(i));
+    }
+
+    from_left
+        .iter()
+        .zip(&from_right)
+        .map(|(l, r)| (l + r) / 2.0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boxes(n: usize) -> Vec<(f64, f64)> {
+        vec![(50.0, 36.0); n]
+    }
+
+    fn overlap(a: &Rect, b: &Rect) -> bool {
+        (a.x - b.x).abs() < (a.width + b.width) / 2.0
+            && (a.y - b.y).abs() < (a.height + b.height) / 2.0
+    }
+
+    fn contains(outer: &Rect, inner: &Rect) -> bool {
+        outer.x - outer.width / 2.0 <= inner.x - inner.width / 2.0
+            && outer.x + outer.width / 2.0 >= inner.x + inner.width / 2.0
+            && outer.y - outer.height / 2.0 <= inner.y - inner.height / 2.0
+            && outer.y + outer.height / 2.0 >= inner.y + inner.height / 2.0
+    }
+
+    fn assert_no_overlaps(layout: &Layout) {
+        for (i, a) in layout.nodes.iter().enumerate() {
+            for b in &layout.nodes[i + 1..] {
+                assert!(!overlap(a, b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_graph() {
+        let layout = layout(&[], &[], &[]);
+        assert!(layout.nodes.is_empty());
+        assert!(layout.edges.is_empty());
+        assert!(layout.cluster.is_none());
+        assert_eq!(layout.height, 0.0);
+    }
+
+    #[test]
+    fn edges_go_down_the_layers() {
+        // 0 -> 1 -> 2, and 0 -> 2 which spans two layers.
+        let edges = [(0, 1), (1, 2), (0, 2)];
+        let layout = layout(&boxes(3), &edges, &[false; 3]);
+        let ys = layout.nodes.iter().map(|r| r.y).collect::<Vec<_>>();
+        assert!(ys[0] < ys[1] && ys[1] < ys[2]);
+        assert_eq!(layout.edges[2].len(), 3);
+        for points in &layout.edges {
+            assert!(points.windows(2).all(|p| p[0].1 < p[1].1));
+        }
+        assert_no_overlaps(&layout);
+    }
+
+    #[test]
+    fn cycles_are_laid_out() {
+        // 0 -> 1 -> 2 -> 1, and 3 -> 3.
+        let edges = [(0, 1), (1, 2), (2, 1), (3, 3)];
+        assert_eq!(back_edges(4, &edges), [false, false, true, true]);
+
+        let layout = layout(&boxes(4), &edges, &[false; 4]);
+        assert!(layout.nodes[0].y < layout.nodes[1].y);
+        assert!(layout.nodes[1].y < layout.nodes[2].y);
+        // The reversed edge still starts at its source.
+        let back = &layout.edges[2];
+        assert_eq!(back[0].1, layout.nodes[2].y - 18.0);
+        assert_eq!(back[back.len() - 1].1, layout.nodes[1].y + 18.0);
+        assert_no_overlaps(&layout);
+    }
+
+    #[test]
+    fn layers_follow_the_longest_path() {
+        let edges = [(0, 1), (1, 2), (0, 2), (3, 2)];
+        assert_eq!(longest_path_layers(4, &edges), [0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn crossings_are_counted() {
+        // Two layers of two nodes, with edges 0 -> 3 and 1 -> 2 that cross.
+        let layers = vec![vec![0, 1], vec![2, 3]];
+        let downs = vec![vec![3], vec![2], vec![], vec![]];
+        assert_eq!(crossings(&layers, &downs, &positions(&layers, 4)), 1);
+
+        let layers = vec![vec![0, 1], vec![3, 2]];
+        assert_eq!(crossings(&layers, &downs, &positions(&layers, 4)), 0);
+    }
+
+    #[test]
+    fn layers_are_placed_without_overlaps() {
+        assert_eq!(place_layer(&[0.0, 200.0], &[50.0, 50.0]), [0.0, 200.0]);
+        // Both nodes want the same spot, so they are pushed apart around it.
+        assert_eq!(place_layer(&[100.0, 100.0], &[50.0, 50.0]), [63.0, 137.0]);
+    }
+
+    #[test]
+    fn cluster_is_apart_from_the_other_nodes() {
+        // 0 -> 1, 2, 3 and 1 -> 4, with 2 and 4 in the cluster.
+        let edges = [(0, 1), (0, 2), (0, 3), (1, 4)];
+        let in_cluster = [false, false, true, false, true];
+        let layout = layout(&boxes(5), &edges, &in_cluster);
+        let cluster = layout.cluster.unwrap();
+
+        for (rect, &member) in layout.nodes.iter().zip(&in_cluster) {
+            if member {
+                assert!(contains(&cluster, rect));
+            } else {
+                assert!(!overlap(&cluster, rect), "{:?} is in the cluster", rect);
+            }
+        }
+        assert!(cluster.y - cluster.height / 2.0 >= 0.0);
+        assert!(cluster.x + cluster.width / 2.0 <= layout.width);
+        assert_no_overlaps(&layout);
+    }
+
+    #[test]
+    fn cluster_fits_above_the_first_layer() {
+        let layout = layout(&boxes(2), &[(0, 1)], &[true, false]);
+        let cluster = layout.cluster.unwrap();
+        assert!(contains(&cluster, &layout.nodes[0]));
+        assert!(cluster.y - cluster.height / 2.0 >= 0.0);
+        assert!(!overlap(&cluster, &layout.nodes[1]));
+    }
+}
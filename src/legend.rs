@@ -0,0 +1,296 @@
+use crate::dep::{Annotation, DepKind, Platform};
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use crate::license::LicenseFamily;
+use crate::project::DeclaredDepsMap;
+use crate::theme::{Attrs, Theme};
+use crate::util::dot_escape;
+use std::io::Write;
+
+const KINDS: [DepKind; 5] = [
+    DepKind::Regular,
+    DepKind::Build,
+    DepKind::Dev,
+    DepKind::Optional,
+    DepKind::Unknown,
+];
+
+/// The styles that appear in a graph, in the order they are listed in the legend. Cycles are out
+/// of scope: they aren't detected or styled apart from other edges, so they have no entry.
+#[derive(Debug, Default)]
+pub struct Legend {
+    /// The kinds of the nodes other than the root. Empty when nodes are colored by license.
+    pub node_kinds: Vec<DepKind>,
+    pub license_families: Vec<LicenseFamily>,
+    pub platforms: Vec<Platform>,
+    pub annotations: Vec<Annotation>,
+    /// An example of a crate with several versions in the graph, whose version is shown.
+    pub duplicate: Option<String>,
+    pub edge_kinds: Vec<DepKind>,
+}
+
+/// A node or edge style of the legend, with its description.
+#[derive(Debug)]
+pub struct Entry {
+    pub id: String,
+    pub label: String,
+    pub attrs: Attrs,
+}
+
+impl Entry {
+    fn new(id: &str, label: &str, attrs: Attrs) -> Self {
+        Entry {
+            id: id.into(),
+            label: label.into(),
+            attrs,
+        }
+    }
+}
+
+impl Legend {
+    pub fn new(dg: &DepGraph, root_deps_map: &DeclaredDepsMap) -> Self {
+        let cfg = &dg.cfg;
+        let deps = || dg.nodes.iter().skip(1);
+        let mut legend = Legend::default();
+
+        if cfg.color_by_license {
+            let mut families = deps().map(|dep| dep.license_family()).collect::<Vec<_>>();
+            families.sort();
+            families.dedup();
+            legend.license_families = families;
+        } else {
+            legend.node_kinds = KINDS
+                .iter()
+                .cloned()
+                .filter(|&kind| deps().any(|dep| dep.kind() == kind))
+                .collect();
+        }
+
+        if cfg.host_target {
+            legend.platforms = [
+                Platform::Target,
+                Platform::Host,
+                Platform::Both,
+                Platform::Unknown,
+            ]
+            .iter()
+            .cloned()
+            .filter(|&platform| deps().any(|dep| dep.platform() == platform))
+            .collect();
+        }
+
+        legend.annotations = [
+            Annotation::Banned,
+            Annotation::Vulnerable,
+            Annotation::Highlighted,
+            Annotation::CompatibleUpdate,
+            Annotation::IncompatibleUpdate,
+        ]
+        .iter()
+        .cloned()
+        .filter(|&annotation| {
+            dg.nodes.iter().any(|dep| match annotation {
+                Annotation::Highlighted => dep.is_highlighted,
+                _ => dep.annotation(cfg) == Some(annotation),
+            })
+        })
+        .collect();
+
+        if !cfg.include_vers {
+            legend.duplicate = deps()
+                .find(|dep| dep.force_write_ver)
+                .map(|dep| dep.display_name(cfg));
+        }
+
+        let edge_kinds = dg
+            .edges
+            .iter()
+            .map(|ed| ed.kind(dg, root_deps_map))
+            .collect::<Vec<_>>();
+        legend.edge_kinds = KINDS
+            .iter()
+            .cloned()
+            .filter(|kind| edge_kinds.contains(kind))
+            .collect();
+
+        legend
+    }
+
+    /// The node styles, in the order they are listed.
+    pub fn node_entries(&self, theme: &Theme) -> Vec<Entry> {
+        let mut entries = vec![];
+
+        let mut root = theme.regular.node.clone();
+        root.merge(&theme.root);
+        entries.push(Entry::new("root", "root", root));
+        for kind in &self.node_kinds {
+            let name = kind.name();
+            entries.push(Entry::new(name, name, theme.kind(*kind).node.clone()));
+        }
+        for family in &self.license_families {
+            let id = format!("license_{:?}", family).to_lowercase();
+            let attrs = Attrs::new(&[("color", family.color())]);
+            entries.push(Entry::new(&id, family.name(), attrs));
+        }
+        for platform in &self.platforms {
+            let id = match platform {
+                Platform::Host => "host",
+                Platform::Target => "target",
+                Platform::Both => "host_target",
+                Platform::Unknown => "platform_unknown",
+            };
+            let attrs = Attrs::new(&[("shape", platform.shape())]);
+            entries.push(Entry::new(id, platform.description(), attrs));
+        }
+        for annotation in &self.annotations {
+            let mut attrs = Attrs::default();
+            annotation.apply(&mut attrs);
+            let id = format!("{:?}", annotation).to_lowercase();
+            entries.push(Entry::new(&id, annotation.description(), attrs));
+        }
+        if let Some(duplicate) = &self.duplicate {
+            let label = format!("{}: several versions", duplicate);
+            entries.push(Entry::new("duplicate", &label, Attrs::default()));
+        }
+
+        entries
+    }
+
+    /// The edge styles, in the order they are listed.
+    pub fn edge_entries(&self, theme: &Theme) -> Vec<Entry> {
+        self.edge_kinds
+            .iter()
+            .map(|kind| Entry::new(kind.name(), kind.name(), theme.kind(*kind).edge.clone()))
+            .collect()
+    }
+}
+
+/// Writes the legend as a DOT cluster. Nodes are listed with their style, and each edge style is
+/// shown by an edge from a point to its description.
+pub fn render_dot<W: Write>(
+    dg: &DepGraph,
+    output: &mut W,
+    root_deps_map: &DeclaredDepsMap,
+) -> CliResult<()> {
+    let legend = Legend::new(dg, root_deps_map);
+    let theme = &dg.cfg.theme;
+
+    writeln!(output, "\tsubgraph cluster_legend {{")?;
+    writeln!(output, "\t\tlabel=\"Legend\";")?;
+    writeln!(output, "\t\tcolor=gray50;")?;
+    writeln!(output)?;
+
+    for entry in legend.node_entries(theme) {
+        let label = dot_escape(&entry.label);
+        if entry.attrs.is_empty() {
+            writeln!(output, "\t\tlegend_{} [label=\"{}\"];", entry.id, label)?;
+        } else {
+            writeln!(
+                output,
+                "\t\tlegend_{} [label=\"{}\", {}];",
+                entry.id, label, entry.attrs
+            )?;
+        }
+    }
+
+    for entry in legend.edge_entries(theme) {
+        writeln!(
+            output,
+            "\t\tlegend_edge_{0}_from [label=\"\", shape=point];",
+            entry.id
+        )?;
+        writeln!(
+            output,
+            "\t\tlegend_edge_{0}_to [label=\"{1}\", shape=plaintext];",
+            entry.id, entry.label
+        )?;
+        if entry.attrs.is_empty() {
+            writeln!(
+                output,
+                "\t\tlegend_edge_{0}_from -> legend_edge_{0}_to;",
+                entry.id
+            )?;
+        } else {
+            writeln!(
+                output,
+                "\t\tlegend_edge_{0}_from -> legend_edge_{0}_to [{1}];",
+                entry.id, entry.attrs
+            )?;
+        }
+    }
+
+    writeln!(output, "\t}}\n")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dep::DepKind::{Build, Regular};
+    use std::collections::HashMap;
+
+    /// A root with a regular and a build dependency, which both depend on `log`.
+    fn graph(args: &[&str]) -> (DepGraph, DeclaredDepsMap) {
+        let mut dg = DepGraph::new(Config::from_args(args));
+        let root = dg.find_or_add("root", "1.0.0");
+        let serde = dg.add_child(root, "serde", "1.0.0");
+        let cc = dg.add_child(root, "cc", "1.0.0");
+        dg.add_child(serde, "log", "0.4.0");
+        dg.add_child(cc, "log", "0.3.0");
+        dg.nodes[serde].license = Some("MIT".into());
+        let mut declared = HashMap::new();
+        declared.insert("serde".to_string(), vec![Regular]);
+        declared.insert("cc".to_string(), vec![Build]);
+        dg.set_resolved_kind(&declared);
+        dg.show_version_on_duplicates();
+        (dg, declared)
+    }
+
+    #[test]
+    fn only_the_styles_in_the_graph_are_listed() {
+        let (dg, declared) = graph(&["--legend"]);
+        let legend = Legend::new(&dg, &declared);
+        assert_eq!(legend.node_kinds, [Regular, Build]);
+        assert_eq!(legend.edge_kinds, [Regular, Build]);
+        assert!(legend.license_families.is_empty());
+        assert!(legend.platforms.is_empty());
+        assert!(legend.annotations.is_empty());
+        assert_eq!(legend.duplicate.as_deref(), Some("log v0.4.0"));
+
+        let mut output = vec![];
+        render_dot(&dg, &mut output, &declared).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "\tsubgraph cluster_legend {\n\
+             \t\tlabel=\"Legend\";\n\
+             \t\tcolor=gray50;\n\n\
+             \t\tlegend_root [label=\"root\", shape=box];\n\
+             \t\tlegend_regular [label=\"regular\"];\n\
+             \t\tlegend_build [label=\"build\", color=purple];\n\
+             \t\tlegend_duplicate [label=\"log v0.4.0: several versions\"];\n\
+             \t\tlegend_edge_regular_from [label=\"\", shape=point];\n\
+             \t\tlegend_edge_regular_to [label=\"regular\", shape=plaintext];\n\
+             \t\tlegend_edge_regular_from -> legend_edge_regular_to;\n\
+             \t\tlegend_edge_build_from [label=\"\", shape=point];\n\
+             \t\tlegend_edge_build_to [label=\"build\", shape=plaintext];\n\
+             \t\tlegend_edge_build_from -> legend_edge_build_to [color=purple, style=dashed];\n\
+             \t}\n\n"
+        );
+    }
+
+    #[test]
+    fn license_families_replace_the_kinds() {
+        let (dg, declared) = graph(&["--legend", "--color-by-license", "--host-target"]);
+        let legend = Legend::new(&dg, &declared);
+        assert!(legend.node_kinds.is_empty());
+        assert_eq!(
+            legend.license_families,
+            [LicenseFamily::Permissive, LicenseFamily::Missing]
+        );
+        assert_eq!(legend.platforms, [Platform::Target, Platform::Host]);
+        assert_eq!(legend.edge_kinds, [Regular, Build]);
+    }
+}
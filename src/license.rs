@@ -0,0 +1,583 @@
+use crate::dep::ResolvedDep;
+use crate::error::{CliError, CliResult};
+use crate::graph::DepGraph;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use toml::Value;
+
+/// License families, ordered from the least to the most restrictive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LicenseFamily {
+    Permissive,
+    WeakCopyleft,
+    StrongCopyleft,
+    /// A license that isn't recognized, or a custom license file.
+    Unknown,
+    /// No license information could be found.
+    Missing,
+}
+
+impl LicenseFamily {
+    /// The color used to draw dependencies with a license of this family.
+    pub fn color(self) -> &'static str {
+        match self {
+            LicenseFamily::Permissive => "forestgreen",
+            LicenseFamily::WeakCopyleft => "darkorange",
+            LicenseFamily::StrongCopyleft => "red",
+            LicenseFamily::Unknown => "goldenrod",
+            LicenseFamily::Missing => "gray",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LicenseFamily::Permissive => "permissive",
+            LicenseFamily::WeakCopyleft => "weak copyleft",
+            LicenseFamily::StrongCopyleft => "strong copyleft",
+            LicenseFamily::Unknown => "unknown",
+            LicenseFamily::Missing => "missing",
+        }
+    }
+}
+
+const PERMISSIVE: &[&str] = &[
+    "0BSD",
+    "APACHE-1.1",
+    "APACHE-2.0",
+    "BSD-1-CLAUSE",
+    "BSD-2-CLAUSE",
+    "BSD-3-CLAUSE",
+    "BSL-1.0",
+    "BZIP2-1.0.6",
+    "CC0-1.0",
+    "ISC",
+    "MIT",
+    "MIT-0",
+    "NCSA",
+    "OPENSSL",
+    "POSTGRESQL",
+    "PYTHON-2.0",
+    "UNICODE-3.0",
+    "UNICODE-DFS-2016",
+    "UNLICENSE",
+    "WTFPL",
+    "X11",
+    "ZLIB",
+];
+
+/// Returns the family of a single SPDX license identifier.
+pub(crate) fn identifier_family(id: &str) -> LicenseFamily {
+    let id = id.trim_end_matches('+').to_uppercase();
+
+    if id.starts_with("LGPL")
+        || id.starts_with("MPL")
+        || id.starts_with("EPL")
+        || id.starts_with("CDDL")
+    {
+        LicenseFamily::WeakCopyleft
+    } else if id.starts_with("GPL")
+        || id.starts_with("AGPL")
+        || id.starts_with("SSPL")
+        || id.starts_with("EUPL")
+        || id.starts_with("OSL")
+    {
+        LicenseFamily::StrongCopyleft
+    } else if PERMISSIVE.contains(&&*id) {
+        LicenseFamily::Permissive
+    } else {
+        LicenseFamily::Unknown
+    }
+}
+
+/// A parsed SPDX license expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LicenseExpr {
+    License {
+        id: String,
+        exception: Option<String>,
+    },
+    And(Vec<LicenseExpr>),
+    Or(Vec<LicenseExpr>),
+}
+
+impl LicenseExpr {
+    /// Parses an SPDX license expression. The legacy `/` separator used by older crates is
+    /// accepted as `OR`, and operators are case-insensitive.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let spaced = s
+            .replace('(', " ( ")
+            .replace(')', " ) ")
+            .replace('/', " OR ");
+        let tokens = spaced.split_whitespace().collect::<Vec<_>>();
+
+        let mut pos = 0;
+        let expr = parse_or(&tokens, &mut pos)?;
+        if pos < tokens.len() {
+            return Err(format!(
+                "Unexpected `{}` in license expression `{}`",
+                tokens[pos], s
+            ));
+        }
+        Ok(expr)
+    }
+
+    /// The family of the license terms that apply. With `OR`, the least restrictive alternative
+    /// can be chosen. With `AND`, the most restrictive license applies.
+    pub fn family(&self) -> LicenseFamily {
+        match self {
+            LicenseExpr::License { id, .. } => identifier_family(id),
+            LicenseExpr::And(exprs) => exprs.iter().map(Self::family).max().unwrap(),
+            LicenseExpr::Or(exprs) => exprs.iter().map(Self::family).min().unwrap(),
+        }
+    }
+}
+
+impl fmt::Display for LicenseExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let write_all = |f: &mut fmt::Formatter, exprs: &[LicenseExpr], op: &str| {
+            for (i, expr) in exprs.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", op)?;
+                }
+                match expr {
+                    LicenseExpr::License { .. } => write!(f, "{}", expr)?,
+                    _ => write!(f, "({})", expr)?,
+                }
+            }
+            Ok(())
+        };
+
+        match self {
+            LicenseExpr::License {
+                id,
+                exception: None,
+            } => write!(f, "{}", id),
+            LicenseExpr::License {
+                id,
+                exception: Some(exception),
+            } => write!(f, "{} WITH {}", id, exception),
+            LicenseExpr::And(exprs) => write_all(f, exprs, "AND"),
+            LicenseExpr::Or(exprs) => write_all(f, exprs, "OR"),
+        }
+    }
+}
+
+fn is_operator(token: &str, op: &str) -> bool {
+    token.eq_ignore_ascii_case(op)
+}
+
+fn parse_or(tokens: &[&str], pos: &mut usize) -> Result<LicenseExpr, String> {
+    let mut exprs = vec![parse_and(tokens, pos)?];
+    while *pos < tokens.len() && is_operator(tokens[*pos], "OR") {
+        *pos += 1;
+        exprs.push(parse_and(tokens, pos)?);
+    }
+
+    Ok(if exprs.len() == 1 {
+        exprs.pop().unwrap()
+    } else {
+        LicenseExpr::Or(exprs)
+    })
+}
+
+fn parse_and(tokens: &[&str], pos: &mut usize) -> Result<LicenseExpr, String> {
+    let mut exprs = vec![parse_atom(tokens, pos)?];
+    while *pos < tokens.len() && is_operator(tokens[*pos], "AND") {
+        *pos += 1;
+        exprs.push(parse_atom(tokens, pos)?);
+    }
+
+    Ok(if exprs.len() == 1 {
+        exprs.pop().unwrap()
+    } else {
+        LicenseExpr::And(exprs)
+    })
+}
+
+fn parse_atom(tokens: &[&str], pos: &mut usize) -> Result<LicenseExpr, String> {
+    let token = match tokens.get(*pos) {
+        Some(token) => *token,
+        None => return Err("Unexpected end of license expression".into()),
+    };
+    *pos += 1;
+
+    if token == "(" {
+        let expr = parse_or(tokens, pos)?;
+        if tokens.get(*pos) != Some(&")") {
+            return Err("Missing `)` in license expression".into());
+        }
+        *pos += 1;
+        return Ok(expr);
+    }
+    if token == ")" || is_operator(token, "OR") || is_operator(token, "AND") {
+        return Err(format!("Unexpected `{}` in license expression", token));
+    }
+
+    let mut exception = None;
+    if *pos < tokens.len() && is_operator(tokens[*pos], "WITH") {
+        exception = tokens.get(*pos + 1).map(|e| e.to_string());
+        if exception.is_none() {
+            return Err("Missing exception after `WITH` in license expression".into());
+        }
+        *pos += 2;
+    }
+
+    Ok(LicenseExpr::License {
+        id: token.to_owned(),
+        exception,
+    })
+}
+
+/// Writes a table of the licenses used in the graph, with the crates using each of them. The most
+/// restrictive licenses are listed first.
+pub fn render_summary<W: Write>(dg: &DepGraph, output: &mut W) -> CliResult<()> {
+    let mut licenses: BTreeMap<(LicenseFamily, String), Vec<String>> = BTreeMap::new();
+    for dep in dg.nodes.iter() {
+        licenses
+            .entry((dep.license_family(), dep.license_name()))
+            .or_default()
+            .push(dep.display_name(&dg.cfg));
+    }
+
+    let width = licenses
+        .keys()
+        .map(|(_, name)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("LICENSE".len());
+
+    writeln!(
+        output,
+        "{:width$}  {:15}  {:>6}  CRATES",
+        "LICENSE",
+        "FAMILY",
+        "COUNT",
+        width = width
+    )?;
+    let mut rows = licenses.into_iter().collect::<Vec<_>>();
+    rows.sort_by(|((fa, na), _), ((fb, nb), _)| fb.cmp(fa).then(na.cmp(nb)));
+    for ((family, name), mut crates) in rows {
+        crates.sort();
+        writeln!(
+            output,
+            "{:width$}  {:15}  {:>6}  {}",
+            name,
+            family.name(),
+            crates.len(),
+            crates.join(", "),
+            width = width
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The `[licenses]` policy of the config file.
+#[derive(Clone, Debug, Default)]
+pub struct LicensePolicy {
+    /// The SPDX identifiers that may be used. If empty, every identifier that isn't denied is
+    /// allowed.
+    pub allow: Vec<String>,
+    /// The SPDX identifiers that may not be used.
+    pub deny: Vec<String>,
+    /// The crates that aren't checked, e.g. because they have a custom license that was reviewed.
+    pub exceptions: Vec<String>,
+}
+
+impl LicensePolicy {
+    pub fn from_toml(table: &Value) -> CliResult<Self> {
+        Ok(LicensePolicy {
+            allow: string_list(table, "licenses", "allow")?,
+            deny: string_list(table, "licenses", "deny")?,
+            exceptions: string_list(table, "licenses", "exceptions")?,
+        })
+    }
+
+    /// Returns the reason why the license of `dep` violates this policy, if it does.
+    pub fn check(&self, dep: &ResolvedDep) -> Option<String> {
+        if self.exceptions.contains(&dep.name) {
+            return None;
+        }
+
+        let license = match (&dep.license, &dep.license_file) {
+            (Some(license), _) => license,
+            (None, Some(file)) => return Some(format!("custom license file `{}`", file)),
+            (None, None) => return Some("no license".into()),
+        };
+        let expr = match LicenseExpr::parse(license) {
+            Ok(expr) => expr,
+            Err(e) => return Some(e),
+        };
+        if self.is_allowed(&expr) {
+            return None;
+        }
+
+        let mut denied = vec![];
+        self.collect_denied(&expr, &mut denied);
+        Some(if denied.is_empty() {
+            format!("`{}` is not allowed", expr)
+        } else {
+            format!("`{}` is denied by {}", expr, denied.join(", "))
+        })
+    }
+
+    /// Whether the license terms of `expr` can be satisfied with the allowed licenses. With `OR`,
+    /// one allowed alternative is enough. With `AND`, all the licenses must be allowed.
+    fn is_allowed(&self, expr: &LicenseExpr) -> bool {
+        match expr {
+            LicenseExpr::License { .. } => {
+                !self.matches(&self.deny, expr)
+                    && (self.allow.is_empty() || self.matches(&self.allow, expr))
+            }
+            LicenseExpr::And(exprs) => exprs.iter().all(|e| self.is_allowed(e)),
+            LicenseExpr::Or(exprs) => exprs.iter().any(|e| self.is_allowed(e)),
+        }
+    }
+
+    /// Whether `list` contains the identifier of a single license, either on its own or along with
+    /// its exception, e.g. `Apache-2.0 WITH LLVM-exception`. Identifiers are compared with
+    /// `normalize_id`.
+    fn matches(&self, list: &[String], expr: &LicenseExpr) -> bool {
+        let (id, exception) = match expr {
+            LicenseExpr::License { id, exception } => (normalize_id(id), exception),
+            _ => return false,
+        };
+        list.iter().any(|l| {
+            let parts = l.split_whitespace().collect::<Vec<_>>();
+            match parts[..] {
+                [l_id] => normalize_id(l_id) == id,
+                [l_id, with, l_exception] if is_operator(with, "WITH") => {
+                    normalize_id(l_id) == id
+                        && exception
+                            .as_ref()
+                            .is_some_and(|e| e.eq_ignore_ascii_case(l_exception))
+                }
+                _ => false,
+            }
+        })
+    }
+
+    fn collect_denied(&self, expr: &LicenseExpr, denied: &mut Vec<String>) {
+        match expr {
+            LicenseExpr::License { .. } => {
+                if self.matches(&self.deny, expr) {
+                    denied.push(expr.to_string());
+                }
+            }
+            LicenseExpr::And(exprs) | LicenseExpr::Or(exprs) => {
+                for e in exprs {
+                    self.collect_denied(e, denied);
+                }
+            }
+        }
+    }
+}
+
+/// Normalizes a license identifier for comparisons. Case is ignored, and the `+`, `-only` and
+/// `-or-later` suffixes of the GNU licenses are dropped, so that `GPL-3.0` also matches
+/// `GPL-3.0-only`, `GPL-3.0-or-later` and `GPL-3.0+`.
+fn normalize_id(id: &str) -> String {
+    let id = id.to_uppercase();
+    let id = id.trim_end_matches('+');
+    id.strip_suffix("-OR-LATER")
+        .or_else(|| id.strip_suffix("-ONLY"))
+        .unwrap_or(id)
+        .to_owned()
+}
+
+/// Reads `table.key` as a list of strings. A missing key is an empty list.
+fn string_list(table: &Value, table_name: &str, key: &str) -> CliResult<Vec<String>> {
+    let values = match table.get(key) {
+        None => return Ok(vec![]),
+        Some(Value::Array(values)) => values,
+        Some(_) => {
+            return Err(CliError::Toml(format!(
+                "`{}.{}` must be an array of strings",
+                table_name, key
+            )))
+        }
+    };
+
+    values
+        .iter()
+        .map(|v| {
+            v.as_str().map(|s| s.to_owned()).ok_or_else(|| {
+                CliError::Toml(format!(
+                    "`{}.{}` must be an array of strings",
+                    table_name, key
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Checks the license of every dependency against `policy` and writes the violations, with the
+/// path from the root that pulls in each violating crate. Returns the number of violations.
+///
+/// The root and the other workspace members belong to the project itself and aren't checked, unlike
+/// path dependencies outside of the workspace.
+pub fn check<W: Write>(dg: &DepGraph, output: &mut W, policy: &LicensePolicy) -> CliResult<usize> {
+    let mut violations = 0;
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        if i == 0 || dep.is_workspace_member {
+            continue;
+        }
+        let reason = match policy.check(dep) {
+            Some(reason) => reason,
+            None => continue,
+        };
+        violations += 1;
+
+        writeln!(output, "{} v{}: {}", dep.name, dep.ver, reason)?;
+        dg.write_path_to(output, i)?;
+    }
+
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license(id: &str) -> LicenseExpr {
+        LicenseExpr::License {
+            id: id.into(),
+            exception: None,
+        }
+    }
+
+    fn policy(allow: &[&str], deny: &[&str]) -> LicensePolicy {
+        LicensePolicy {
+            allow: allow.iter().map(|l| l.to_string()).collect(),
+            deny: deny.iter().map(|l| l.to_string()).collect(),
+            exceptions: vec!["reviewed".into()],
+        }
+    }
+
+    fn dep(name: &str, license: Option<&str>) -> ResolvedDep {
+        let mut dep = ResolvedDep::new(name.into(), "1.0.0".into());
+        dep.license = license.map(|l| l.into());
+        dep
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            LicenseExpr::parse("MIT OR Apache-2.0 AND BSD-3-Clause"),
+            Ok(LicenseExpr::Or(vec![
+                license("MIT"),
+                LicenseExpr::And(vec![license("Apache-2.0"), license("BSD-3-Clause")]),
+            ]))
+        );
+        assert_eq!(
+            LicenseExpr::parse("MIT and Zlib or ISC"),
+            Ok(LicenseExpr::Or(vec![
+                LicenseExpr::And(vec![license("MIT"), license("Zlib")]),
+                license("ISC"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn parentheses_group_expressions() {
+        let expr = LicenseExpr::parse("(MIT OR Apache-2.0) AND (BSD-3-Clause)").unwrap();
+        assert_eq!(
+            expr,
+            LicenseExpr::And(vec![
+                LicenseExpr::Or(vec![license("MIT"), license("Apache-2.0")]),
+                license("BSD-3-Clause"),
+            ])
+        );
+        assert_eq!(expr.to_string(), "(MIT OR Apache-2.0) AND BSD-3-Clause");
+        assert_eq!(
+            LicenseExpr::parse("MIT/Apache-2.0"),
+            Ok(LicenseExpr::Or(vec![license("MIT"), license("Apache-2.0")]))
+        );
+    }
+
+    #[test]
+    fn with_attaches_an_exception() {
+        let expr = LicenseExpr::parse("Apache-2.0 WITH LLVM-exception OR MIT").unwrap();
+        assert_eq!(
+            expr,
+            LicenseExpr::Or(vec![
+                LicenseExpr::License {
+                    id: "Apache-2.0".into(),
+                    exception: Some("LLVM-exception".into()),
+                },
+                license("MIT"),
+            ])
+        );
+        assert_eq!(expr.to_string(), "Apache-2.0 WITH LLVM-exception OR MIT");
+    }
+
+    #[test]
+    fn malformed_expressions_are_errors() {
+        for s in &[
+            "",
+            "MIT AND",
+            "OR MIT",
+            "(MIT",
+            "MIT)",
+            "MIT WITH",
+            "MIT Apache-2.0",
+        ] {
+            assert!(LicenseExpr::parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn gnu_suffixes_match_the_base_identifier() {
+        let policy = policy(&[], &["GPL-3.0", "lgpl-2.1-or-later"]);
+        for l in &[
+            "GPL-3.0",
+            "GPL-3.0-only",
+            "GPL-3.0-or-later",
+            "GPL-3.0+",
+            "LGPL-2.1-only",
+        ] {
+            assert!(policy.check(&dep("a", Some(l))).is_some(), "{}", l);
+        }
+        for l in &["MIT", "LGPL-3.0-only", "AGPL-3.0-only", "GPL-2.0-or-later"] {
+            assert_eq!(policy.check(&dep("a", Some(l))), None, "{}", l);
+        }
+    }
+
+    #[test]
+    fn policy_evaluates_or_and_and() {
+        let policy = policy(&["MIT", "Apache-2.0 WITH LLVM-exception"], &["GPL-3.0"]);
+        let check = |l| policy.check(&dep("a", Some(l)));
+
+        assert_eq!(check("MIT OR GPL-3.0-only"), None);
+        assert_eq!(check("Apache-2.0 WITH LLVM-exception"), None);
+        assert_eq!(
+            check("MIT AND GPL-3.0-only"),
+            Some("`MIT AND GPL-3.0-only` is denied by GPL-3.0-only".into())
+        );
+        assert_eq!(
+            check("Apache-2.0"),
+            Some("`Apache-2.0` is not allowed".into())
+        );
+        assert_eq!(
+            check("Apache-2.0 WITH Classpath-exception-2.0"),
+            Some("`Apache-2.0 WITH Classpath-exception-2.0` is not allowed".into())
+        );
+    }
+
+    #[test]
+    fn policy_reports_missing_licenses_and_skips_exceptions() {
+        let policy = policy(&["MIT"], &[]);
+        assert_eq!(policy.check(&dep("a", None)), Some("no license".into()));
+
+        let mut custom = dep("a", None);
+        custom.license_file = Some("LICENSE".into());
+        assert_eq!(
+            policy.check(&custom),
+            Some("custom license file `LICENSE`".into())
+        );
+
+        assert_eq!(policy.check(&dep("reviewed", None)), None);
+        assert_eq!(policy.check(&dep("reviewed", Some("GPL-3.0"))), None);
+    }
+}
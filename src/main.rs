@@ -12,16 +12,33 @@
 extern crate clap;
 extern crate toml;
 
+mod advisory;
+mod bans;
 mod config;
+mod cyclonedx;
 mod dep;
 mod error;
+mod gexf;
 mod graph;
+mod graphml;
+mod graphviz;
+mod html;
+mod index;
+mod layout;
+mod legend;
+mod license;
+mod manifest;
+mod plantuml;
 mod project;
+mod spdx;
+mod svg;
+mod theme;
 mod util;
 
-use crate::config::Config;
+use crate::config::{Config, Format};
 use crate::error::{CliError, CliResult};
 use crate::project::Project;
+use crate::theme::Theme;
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use std::fs::File;
 use std::io::{self, BufWriter};
@@ -29,6 +46,10 @@ use std::path::Path;
 use std::path::PathBuf;
 
 fn parse_cli<'a>() -> ArgMatches<'a> {
+    cli().get_matches()
+}
+
+fn cli<'a, 'b>() -> App<'a, 'b> {
     App::new("cargo-deps")
         .version(crate_version!())
         .bin_name("cargo")
@@ -39,11 +60,36 @@ fn parse_cli<'a>() -> ArgMatches<'a> {
                 .about(crate_description!())
                 .args_from_usage(
                     "
-                    -o  --dot-file [PATH] 'Output file [default: stdout]'
+                    -o  --dot-file [PATH] 'Output file [default: stdout]. With the DOT \
+                        format, files ending in .png, .svg, .pdf, .jpg or .gif are rendered \
+                        with Graphviz'
+                        --check-bans 'Report the deps banned by the [bans] policy of the \
+                        config file instead of rendering the graph. Without this flag, banned deps \
+                        are highlighted in the graph'
+                        --check-licenses 'Check the licenses of the deps against the \
+                        [licenses] policy of the config file instead of rendering the graph'
+                        --concentrate 'Merge the parallel edges of the DOT layout'
+                        --color-by-license 'Color deps by license family instead of by kind: \
+                        permissive (green), weak copyleft (dark orange), strong copyleft (red), \
+                        unknown (goldenrod) or missing (gray)'
                         --filter [DEPNAMES] ... 'Only display provided deps'
-                        --include-orphans 'Don't purge orphan nodes (yellow). \
-                        This is useful in some workspaces'
+                        --highlight [PATTERN] ... 'Fill the deps whose name or name@version \
+                        matches the glob pattern (gold) and bold their edges'
+                        --host-target 'Shape deps compiled only for the target as ellipses, \
+                        only for the host as hexagons, for both the host and the target as \
+                        octagons, and for an unknown platform as diamonds'
+                        --include-orphans 'Keep the deps that can't be reached from the \
+                        root, including cycles that nothing else depends on. This is useful in some \
+                        workspaces'
                     -I, --include-versions 'Include the dependency version on nodes'
+                        --legend 'Add a legend of the styles used in the graph \
+                        (DOT, SVG and PlantUML)'
+                        --shade-outdated 'Shade deps with a newer semver-compatible release \
+                        (dark gray) or only newer incompatible releases (light gray)'
+                        --sort 'Order nodes by name, version and source, and edges by \
+                        their ends, so that the same graph always gives the same output'
+                        --stable-ids 'Identify the nodes of the DOT output by name@version \
+                        instead of by index, so that they don't change between runs'
                         --subgraph [DEPNAMES] ... 'Group provided deps in their own subgraph'
 
                         --all-deps 'Include all dependencies in the graph. \
@@ -55,21 +101,101 @@ fn parse_cli<'a>() -> ArgMatches<'a> {
                     ",
                 )
                 .args(&[
+                    Arg::from_usage(
+                        "--advisory-db [PATH] 'Local checkout of the RustSec advisory database. \
+                         Vulnerable deps are highlighted in the graph'",
+                    ),
+                    Arg::from_usage(
+                        "--audit 'Report the deps affected by the advisories of the advisory \
+                         database instead of rendering the graph'",
+                    )
+                    .requires("advisory-db"),
+                    Arg::from_usage("--config [PATH] 'TOML file with the policies to check'")
+                        .validator(is_file),
+                    Arg::from_usage(
+                        "--dim-others 'Gray out the deps that aren't highlighted or next to a \
+                         highlighted dep'",
+                    )
+                    .requires("highlight"),
+                    Arg::from_usage(
+                        "--edge-attr [KEY=VALUE]... 'Default attribute of the edges of the DOT \
+                         output'",
+                    )
+                    .number_of_values(1)
+                    .validator(is_attr),
+                    Arg::from_usage("--engine [ENGINE] 'Graphviz layout engine'")
+                        .possible_values(graphviz::ENGINES),
+                    Arg::from_usage(
+                        "--font [FONT] 'Font of the graph, node and edge labels of the DOT output'",
+                    ),
+                    Arg::from_usage(
+                        "--graph-attr [KEY=VALUE]... 'Attribute of the graph of the DOT output'",
+                    )
+                    .number_of_values(1)
+                    .validator(is_attr),
+                    Arg::from_usage(
+                        "--node-attr [KEY=VALUE]... 'Default attribute of the nodes of the DOT \
+                         output'",
+                    )
+                    .number_of_values(1)
+                    .validator(is_attr),
+                    Arg::from_usage("--format [FORMAT] 'Output format'")
+                        .possible_values(Format::NAMES)
+                        .default_value("dot"),
+                    Arg::from_usage(
+                        "--index [PATH] 'Local checkout of the crates.io index to look for newer \
+                         releases in [default: the index cache of cargo]'",
+                    ),
+                    Arg::from_usage(
+                        "--keep-root-edges 'Keep the edges of the root, which are declared in its \
+                         manifest, in the --transitive-reduction'",
+                    )
+                    .requires("transitive-reduction"),
                     Arg::from_usage("--manifest-path [PATH] 'Specify location of manifest file'")
                         .default_value("Cargo.toml")
                         .validator(is_file),
+                    Arg::from_usage(
+                        "--rank-by-depth 'Place the deps at the same shortest distance from the \
+                         root side by side in the DOT output'",
+                    ),
+                    Arg::from_usage(
+                        "--rank-labels 'Label the layers of --rank-by-depth: root, direct, \
+                         level 2, ...'",
+                    )
+                    .requires("rank-by-depth"),
+                    Arg::from_usage("--rankdir [DIR] 'Direction of the DOT layout [default: TB]'")
+                        .possible_values(&["TB", "LR", "BT", "RL"]),
+                    Arg::from_usage(
+                        "--ranksep [INCHES] 'Space between the ranks of the DOT layout'",
+                    ),
+                    Arg::from_usage(
+                        "--splines [SPLINES] 'How the edges of the DOT layout are drawn'",
+                    )
+                    .possible_values(&["spline", "line", "polyline", "ortho", "curved", "none"]),
                     Arg::from_usage("--subgraph-name [NAME] 'Optional name of subgraph'")
                         .requires("subgraph"),
+                    Arg::from_usage(
+                        "--theme [THEME] 'Theme of the DOT output. The [theme] table of the \
+                         config file can customize it [default: default]'",
+                    )
+                    .possible_values(Theme::NAMES),
+                    Arg::from_usage(
+                        "--transitive-reduction 'Remove the edges implied by longer paths, e.g. \
+                         A -> C when A -> B -> C is of the same kind'",
+                    ),
+                    Arg::from_usage(
+                        "--vendor-dir [PATH] 'Directory of vendored sources to read the \
+                         manifests of deps from, before the local registry cache'",
+                    ),
                 ]),
         )
-        .get_matches()
 }
 
 fn main() {
     let m = parse_cli();
 
     if let Some(m) = m.subcommand_matches("deps") {
-        let cfg = Config::from_matches(&m).unwrap_or_else(|e| e.exit());
+        let cfg = Config::from_matches(m).unwrap_or_else(|e| e.exit());
         execute(cfg).map_err(|e| e.exit()).unwrap();
     }
 }
@@ -97,26 +223,44 @@ fn execute(cfg: Config) -> CliResult<()> {
     let lock_file = format!("{}.lock", &manifest[0..manifest.len() - 5]);
     let lock_path = util::find_manifest_file(&PathBuf::from(lock_file))?;
 
-    // Graph the project.
+    // Render DOT output to images with Graphviz when the output file has an image extension.
     let dot_file = cfg.dot_file.clone();
+    let image_type = match &dot_file {
+        Some(file) if cfg.format == Format::Dot && !cfg.is_check() => {
+            graphviz::image_type(Path::new(file))
+        }
+        _ => None,
+    };
+
+    // Graph the project.
     let project = Project::with_config(cfg)?;
     let (graph, root_deps_map) = project.graph(manifest_path, lock_path)?;
 
     // Render the dot file.
-    match dot_file {
-        None => {
+    match (dot_file, image_type) {
+        (Some(file), Some(image_type)) => {
+            graphviz::render_image(graph, &root_deps_map, Path::new(&file), &image_type)
+        }
+        (None, _) => {
             let o = io::stdout();
             let mut bw = BufWriter::new(o.lock());
             graph.render_to(&mut bw, &root_deps_map)
         }
-        Some(file) => {
-            let o = File::create(&Path::new(&file)).expect("Failed to create file");
+        (Some(file), None) => {
+            let o = File::create(Path::new(&file)).expect("Failed to create file");
             let mut bw = BufWriter::new(o);
             graph.render_to(&mut bw, &root_deps_map)
         }
     }
 }
 
+fn is_attr(s: String) -> Result<(), String> {
+    match s.find('=') {
+        Some(i) if i > 0 => Ok(()),
+        _ => Err(format!("'{}' isn't of the form KEY=VALUE", s)),
+    }
+}
+
 fn is_file(s: String) -> Result<(), String> {
     let p = Path::new(&*s);
     if p.file_name().is_none() {
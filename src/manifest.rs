@@ -0,0 +1,342 @@
+use crate::util;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// The parts of a package manifest that cargo-deps uses.
+#[derive(Debug, Default, PartialEq)]
+pub struct PackageManifest {
+    pub deps: Vec<String>,
+    pub build_deps: Vec<String>,
+    pub dev_deps: Vec<String>,
+    pub is_proc_macro: bool,
+    pub license: Option<String>,
+    pub license_file: Option<String>,
+}
+
+impl PackageManifest {
+    /// Reads a manifest. `workspace` is the `[workspace.package]` table of the workspace root, if
+    /// any, which provides the fields that members inherit with `field.workspace = true`.
+    pub fn from_toml(toml: &Value, workspace: Option<&Value>) -> Self {
+        let mut manifest = PackageManifest::default();
+
+        let mut add_tables = |table: &Value| {
+            collect_dep_names(table.get("dependencies"), &mut manifest.deps);
+            collect_dep_names(table.get("build-dependencies"), &mut manifest.build_deps);
+            collect_dep_names(table.get("build_dependencies"), &mut manifest.build_deps);
+            collect_dep_names(table.get("dev-dependencies"), &mut manifest.dev_deps);
+            collect_dep_names(table.get("dev_dependencies"), &mut manifest.dev_deps);
+        };
+
+        add_tables(toml);
+        if let Some(Value::Table(targets)) = toml.get("target") {
+            for target in targets.values() {
+                add_tables(target);
+            }
+        }
+
+        if let Some(package) = toml.get("package") {
+            let field = |key| {
+                let value = package.get(key)?;
+                let value = match value.get("workspace").and_then(Value::as_bool) {
+                    Some(true) => workspace?.get(key)?,
+                    _ => value,
+                };
+                value.as_str().map(str::to_owned)
+            };
+            manifest.license = field("license");
+            manifest.license_file = field("license-file");
+        }
+
+        if let Some(lib) = toml.get("lib") {
+            manifest.is_proc_macro = [lib.get("proc-macro"), lib.get("proc_macro")]
+                .iter()
+                .any(|v| v.and_then(Value::as_bool) == Some(true));
+        }
+
+        manifest
+    }
+
+    /// Returns true if `name` is only ever declared as a dev-dependency. Cargo ignores these for
+    /// every package but the root.
+    pub fn is_dev_only(&self, name: &str) -> bool {
+        self.dev_deps.iter().any(|dep| dep == name)
+            && !self.deps.iter().any(|dep| dep == name)
+            && !self.build_deps.iter().any(|dep| dep == name)
+    }
+}
+
+/// Adds the package names of the dependencies in a dependency table, taking renames into account.
+fn collect_dep_names(table: Option<&Value>, names: &mut Vec<String>) {
+    if let Some(Value::Table(table)) = table {
+        for (name, dep) in table.iter() {
+            let name = match dep.get("package").and_then(Value::as_str) {
+                Some(package) => package,
+                None => name,
+            };
+            names.push(name.to_owned());
+        }
+    }
+}
+
+/// Locates the manifests of resolved packages on disk, without accessing the network.
+///
+/// Registry packages are looked up in the vendor directory, if any, then in the local cargo
+/// registry cache. Path packages are found by following the `path` dependencies and workspace
+/// members of the root manifest.
+#[derive(Debug)]
+pub struct ManifestFinder {
+    vendor_dir: Option<PathBuf>,
+    registry_dirs: Vec<PathBuf>,
+    path_manifests: HashMap<(String, String), PathBuf>,
+    // The manifests of the root packages and of the members of their workspace.
+    member_manifests: HashSet<PathBuf>,
+}
+
+impl ManifestFinder {
+    pub fn new(root_manifests: &[&Path], vendor_dir: Option<PathBuf>) -> Self {
+        let mut finder = ManifestFinder {
+            vendor_dir,
+            registry_dirs: registry_src_dirs(),
+            path_manifests: HashMap::new(),
+            member_manifests: HashSet::new(),
+        };
+
+        let mut visited = vec![];
+        for manifest_path in root_manifests {
+            finder.add_path_package(manifest_path, true, &mut visited);
+        }
+
+        finder
+    }
+
+    /// Finds and parses the manifest of the given package. `source` is the `source` field of the
+    /// package in the lock file, which is absent for path packages.
+    pub fn find(&self, name: &str, ver: &str, source: Option<&str>) -> Option<PackageManifest> {
+        let path = self.find_path(name, ver, source)?;
+        let toml = util::toml_from_file(&path).ok()?;
+        let workspace = match source {
+            None => workspace_package(&path),
+            Some(_) => None,
+        };
+        Some(PackageManifest::from_toml(&toml, workspace.as_ref()))
+    }
+
+    /// Whether the given path package is one of the root packages or a member of their workspace,
+    /// as opposed to a path dependency outside of it.
+    pub fn is_workspace_member(&self, name: &str, ver: &str) -> bool {
+        self.path_manifests
+            .get(&(name.to_owned(), ver.to_owned()))
+            .is_some_and(|path| self.member_manifests.contains(path))
+    }
+
+    fn find_path(&self, name: &str, ver: &str, source: Option<&str>) -> Option<PathBuf> {
+        if let (Some(vendor_dir), Some(_)) = (&self.vendor_dir, source) {
+            if let Some(path) = find_vendored(vendor_dir, name, ver) {
+                return Some(path);
+            }
+        }
+
+        match source {
+            None => self
+                .path_manifests
+                .get(&(name.to_owned(), ver.to_owned()))
+                .cloned(),
+            Some(source) if source.starts_with("registry+") || source.starts_with("sparse+") => {
+                let dir_name = format!("{}-{}", name, ver);
+                self.registry_dirs
+                    .iter()
+                    .map(|dir| dir.join(&dir_name).join("Cargo.toml"))
+                    .find(|path| path.is_file())
+            }
+            Some(_) => None,
+        }
+    }
+
+    /// Records the package at `manifest_path` and, recursively, its path dependencies and
+    /// workspace members. `is_member` is set for the root packages and the workspace members.
+    fn add_path_package(
+        &mut self,
+        manifest_path: &Path,
+        is_member: bool,
+        visited: &mut Vec<PathBuf>,
+    ) {
+        let manifest_path = match fs::canonicalize(manifest_path) {
+            Ok(path) => path,
+            Err(_) => return,
+        };
+        // A member can also be reached first as a path dependency of another member.
+        if is_member {
+            self.member_manifests.insert(manifest_path.clone());
+        }
+        if visited.contains(&manifest_path) {
+            return;
+        }
+        visited.push(manifest_path.clone());
+
+        let toml = match util::toml_from_file(&manifest_path) {
+            Ok(toml) => toml,
+            Err(_) => return,
+        };
+        let dir = manifest_path.parent().unwrap().to_path_buf();
+
+        if let Some(package) = toml.get("package") {
+            if let (Some(name), Some(ver)) = (
+                package.get("name").and_then(Value::as_str),
+                package.get("version").and_then(Value::as_str),
+            ) {
+                self.path_manifests
+                    .insert((name.to_owned(), ver.to_owned()), manifest_path.clone());
+            }
+        }
+
+        let mut dep_dirs = vec![];
+        let mut member_dirs = vec![];
+        let mut add_path_deps = |table: Option<&Value>| {
+            if let Some(Value::Table(table)) = table {
+                for dep in table.values() {
+                    if let Some(path) = dep.get("path").and_then(Value::as_str) {
+                        dep_dirs.push(dir.join(path));
+                    }
+                }
+            }
+        };
+        let mut tables = vec![&toml];
+        if let Some(Value::Table(targets)) = toml.get("target") {
+            tables.extend(targets.values());
+        }
+        for table in tables {
+            add_path_deps(table.get("dependencies"));
+            add_path_deps(table.get("build-dependencies"));
+            add_path_deps(table.get("dev-dependencies"));
+        }
+
+        if let Some(Value::Array(members)) = toml.get("workspace").and_then(|ws| ws.get("members"))
+        {
+            for member in members.iter().filter_map(Value::as_str) {
+                if let Some(parent) = member.strip_suffix("/*") {
+                    let parent = dir.join(parent);
+                    if let Ok(entries) = fs::read_dir(parent) {
+                        member_dirs.extend(entries.filter_map(|e| e.ok()).map(|e| e.path()));
+                    }
+                } else {
+                    member_dirs.push(dir.join(member));
+                }
+            }
+        }
+
+        for member_dir in member_dirs {
+            self.add_path_package(&member_dir.join("Cargo.toml"), true, visited);
+        }
+        for dep_dir in dep_dirs {
+            self.add_path_package(&dep_dir.join("Cargo.toml"), false, visited);
+        }
+    }
+}
+
+/// Returns the `[workspace.package]` table of the workspace that the manifest at `manifest_path`
+/// belongs to: that of the closest manifest with a `[workspace]` table, starting from its own.
+/// Published packages have their inherited fields filled in, so this only matters for path
+/// packages.
+fn workspace_package(manifest_path: &Path) -> Option<Value> {
+    for dir in manifest_path.parent()?.ancestors() {
+        let toml = match util::toml_from_file(dir.join("Cargo.toml")) {
+            Ok(toml) => toml,
+            Err(_) => continue,
+        };
+        if let Some(workspace) = toml.get("workspace") {
+            return workspace.get("package").cloned();
+        }
+    }
+    None
+}
+
+/// Finds a package in a directory created by `cargo vendor`, where packages are stored in
+/// `<name>` directories, or in `<name>-<version>` directories when several versions are vendored.
+fn find_vendored(vendor_dir: &Path, name: &str, ver: &str) -> Option<PathBuf> {
+    let versioned = vendor_dir
+        .join(format!("{}-{}", name, ver))
+        .join("Cargo.toml");
+    if versioned.is_file() {
+        return Some(versioned);
+    }
+
+    let unversioned = vendor_dir.join(name).join("Cargo.toml");
+    let toml = util::toml_from_file(&unversioned).ok()?;
+    let found_ver = toml.get("package")?.get("version")?.as_str()?;
+    if found_ver == ver {
+        Some(unversioned)
+    } else {
+        None
+    }
+}
+
+/// Returns the cargo home directory, honoring `$CARGO_HOME`.
+pub fn cargo_home() -> Option<PathBuf> {
+    if let Some(home) = env::var_os("CARGO_HOME") {
+        return Some(PathBuf::from(home));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo"))
+}
+
+/// Returns the directories under `~/.cargo/registry/src` where cargo unpacks registry packages.
+fn registry_src_dirs() -> Vec<PathBuf> {
+    let src = match cargo_home() {
+        Some(home) => home.join("registry").join("src"),
+        None => return vec![],
+    };
+
+    match fs::read_dir(src) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inherited_licenses_are_read_from_the_workspace() {
+        let workspace = "license = \"MIT OR Apache-2.0\"".parse::<Value>().unwrap();
+        let member = "[package]\nname = \"a\"\nlicense.workspace = true\n\
+                      license-file = { workspace = true }\n"
+            .parse::<Value>()
+            .unwrap();
+
+        let manifest = PackageManifest::from_toml(&member, Some(&workspace));
+        assert_eq!(manifest.license.as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(manifest.license_file, None);
+
+        let manifest = PackageManifest::from_toml(&member, None);
+        assert_eq!(manifest.license, None);
+    }
+
+    #[test]
+    fn workspace_members_find_the_workspace_root() {
+        let dir = env::temp_dir().join(format!("cargo-deps-manifest-{}", std::process::id()));
+        fs::create_dir_all(dir.join("crates").join("a")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nlicense = \"MIT\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("crates").join("a").join("Cargo.toml"),
+            "[package]\nname = \"a\"\nversion = \"1.0.0\"\nlicense.workspace = true\n",
+        )
+        .unwrap();
+
+        let finder = ManifestFinder::new(&[&dir.join("Cargo.toml")], None);
+        let manifest = finder.find("a", "1.0.0", None);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(manifest.unwrap().license.as_deref(), Some("MIT"));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::dep::DepKind;
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use crate::legend::Legend;
+use crate::project::DeclaredDepsMap;
+use std::io::Write;
+
+const KINDS: [DepKind; 4] = [
+    DepKind::Build,
+    DepKind::Dev,
+    DepKind::Optional,
+    DepKind::Unknown,
+];
+
+/// Writes the graph as a PlantUML component diagram. Dependency kinds are mapped to stereotypes
+/// that are colored like in the DOT output, and the subgraph is mapped to a package.
+pub fn render<W: Write>(
+    dg: &DepGraph,
+    output: &mut W,
+    root_deps_map: &DeclaredDepsMap,
+) -> CliResult<()> {
+    writeln!(output, "@startuml")?;
+    writeln!(output, "hide stereotype")?;
+    writeln!(output, "skinparam component {{")?;
+    writeln!(output, "\tBackgroundColor white")?;
+    writeln!(output, "\tBorderColor black")?;
+    writeln!(output, "\tBorderThickness<<Root>> 2")?;
+    for kind in KINDS.iter() {
+        writeln!(output, "\tBorderColor<<{:?}>> {}", kind, kind.color())?;
+        writeln!(output, "\tFontColor<<{:?}>> {}", kind, kind.color())?;
+    }
+    writeln!(output, "}}")?;
+    writeln!(output, "skinparam package {{")?;
+    writeln!(output, "\tBorderColor brown")?;
+    writeln!(output, "\tStyle rectangle")?;
+    writeln!(output, "}}")?;
+    writeln!(output)?;
+
+    let in_subgraph = |name: &String| {
+        dg.cfg
+            .subgraph
+            .as_ref()
+            .is_some_and(|sub_deps| sub_deps.contains(name))
+    };
+
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        if !in_subgraph(&dep.name) {
+            write_component(output, dg, i, "")?;
+        }
+    }
+
+    if dg.cfg.subgraph.is_some() {
+        let name = dg.cfg.subgraph_name.as_deref().unwrap_or("Subgraph");
+        writeln!(output)?;
+        writeln!(output, "package \"{}\" {{", quote(name))?;
+        for (i, dep) in dg.nodes.iter().enumerate() {
+            if in_subgraph(&dep.name) {
+                write_component(output, dg, i, "\t")?;
+            }
+        }
+        writeln!(output, "}}")?;
+    }
+    writeln!(output)?;
+
+    for ed in &dg.edges {
+        match ed.kind(dg, root_deps_map) {
+            DepKind::Regular => writeln!(output, "n{} --> n{}", ed.0, ed.1)?,
+            kind => writeln!(output, "n{} -[#{},dashed]-> n{}", ed.0, kind.color(), ed.1)?,
+        }
+    }
+
+    if dg.cfg.legend {
+        let legend = Legend::new(dg, root_deps_map);
+        writeln!(output)?;
+        writeln!(output, "legend right")?;
+        writeln!(output, "\t|= Kind |= Nodes |= Edges |")?;
+        for kind in [
+            DepKind::Regular,
+            DepKind::Build,
+            DepKind::Dev,
+            DepKind::Optional,
+            DepKind::Unknown,
+        ] {
+            let has_nodes = legend.node_kinds.contains(&kind);
+            let has_edges = legend.edge_kinds.contains(&kind);
+            if !has_nodes && !has_edges {
+                continue;
+            }
+            let edge_style = if kind == DepKind::Regular {
+                "solid"
+            } else {
+                "dashed"
+            };
+            writeln!(
+                output,
+                "\t| <color:{0}>{1}</color> | {2} | {3} |",
+                kind.color(),
+                kind.name(),
+                if has_nodes { "yes" } else { "" },
+                if has_edges { edge_style } else { "" }
+            )?;
+        }
+        if let Some(duplicate) = &legend.duplicate {
+            writeln!(output, "\t| {} | several versions | |", duplicate)?;
+        }
+        writeln!(output, "endlegend")?;
+    }
+
+    writeln!(output, "@enduml")?;
+
+    Ok(())
+}
+
+fn write_component<W: Write>(
+    output: &mut W,
+    dg: &DepGraph,
+    i: usize,
+    indent: &str,
+) -> CliResult<()> {
+    let dep = &dg.nodes[i];
+    let stereotype = if i == 0 {
+        "Root".to_owned()
+    } else {
+        format!("{:?}", dep.kind())
+    };
+
+    writeln!(
+        output,
+        "{}component \"{}\" as n{} <<{}>>",
+        indent,
+        quote(&dep.display_name(&dg.cfg)),
+        i,
+        stereotype
+    )?;
+    Ok(())
+}
+
+/// PlantUML has no way to escape double quotes in quoted names, so they are replaced.
+fn quote(s: &str) -> String {
+    s.replace('"', "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dep::DepKind::{Build, Dev, Regular};
+    use std::collections::HashMap;
+
+    #[test]
+    fn kinds_subgraph_and_legend() {
+        let cfg = Config::from_args(&[
+            "--dev-deps",
+            "--legend",
+            "--subgraph",
+            "serde",
+            "cc",
+            "--subgraph-name",
+            "Core \"crates\"",
+        ]);
+        let mut dg = DepGraph::new(cfg);
+        let root = dg.find_or_add("root", "1.0.0");
+        dg.add_child(root, "serde", "1.0.0");
+        dg.add_child(root, "cc", "1.0.0");
+        dg.add_child(root, "criterion", "0.3.0");
+        let mut declared = HashMap::new();
+        declared.insert("serde".to_string(), vec![Regular]);
+        declared.insert("cc".to_string(), vec![Build]);
+        declared.insert("criterion".to_string(), vec![Dev]);
+        dg.set_resolved_kind(&declared);
+
+        let mut output = vec![];
+        render(&dg, &mut output, &declared).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        for expected in [
+            "\tBorderColor<<Build>> purple\n\tFontColor<<Build>> purple\n",
+            "\tBorderColor<<Dev>> blue\n\tFontColor<<Dev>> blue\n",
+            "component \"root\" as n0 <<Root>>\n\
+             component \"criterion\" as n3 <<Dev>>\n\n\
+             package \"Core 'crates'\" {\n\
+             \tcomponent \"serde\" as n1 <<Regular>>\n\
+             \tcomponent \"cc\" as n2 <<Build>>\n\
+             }\n\n",
+            "n0 --> n1\nn0 -[#purple,dashed]-> n2\nn0 -[#blue,dashed]-> n3\n",
+            "legend right\n\
+             \t|= Kind |= Nodes |= Edges |\n\
+             \t| <color:black>regular</color> | yes | solid |\n\
+             \t| <color:purple>build</color> | yes | dashed |\n\
+             \t| <color:blue>dev</color> | yes | dashed |\n\
+             endlegend\n@enduml\n",
+        ] {
+            assert!(
+                output.contains(expected),
+                "{}\nnot in:\n{}",
+                expected,
+                output
+            );
+        }
+    }
+}
@@ -2,6 +2,7 @@ use crate::config::Config;
 use crate::dep::{DeclaredDep, DepKind};
 use crate::error::{CliError, CliResult};
 use crate::graph::DepGraph;
+use crate::manifest::ManifestFinder;
 use crate::util;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -26,7 +27,12 @@ impl Project {
     ) -> CliResult<(DepGraph, DeclaredDepsMap)> {
         let (root_deps, root_name, root_version) = self.parse_root_deps(&manifest_path)?;
 
-        let mut dg = self.parse_lock_file(lock_path, &root_deps, &root_name, &root_version)?;
+        let workspace_manifest = lock_path.with_file_name("Cargo.toml");
+        let vendor_dir = self.cfg.vendor_dir.as_ref().map(PathBuf::from);
+        let finder = ManifestFinder::new(&[&manifest_path, &workspace_manifest], vendor_dir);
+
+        let mut dg =
+            self.parse_lock_file(lock_path, &finder, &root_deps, &root_name, &root_version)?;
 
         // Set node 0 to be the root.
         if !dg.set_root(&root_name, &root_version) {
@@ -36,7 +42,7 @@ impl Project {
         let mut root_deps_map = HashMap::new();
         for dep in root_deps.iter() {
             let (name, kind) = (dep.name.clone(), dep.kind);
-            let kinds: &mut Vec<DepKind> = root_deps_map.entry(name).or_insert_with(|| vec![]);
+            let kinds: &mut Vec<DepKind> = root_deps_map.entry(name).or_insert_with(Vec::new);
             kinds.push(kind);
         }
 
@@ -63,7 +69,7 @@ impl Project {
         let (root_name, root_version) = {
             if let Some(table) = manifest_toml.get("package") {
                 if let Some(table) = table.as_table() {
-                    if let (Some(&Value::String(ref n)), Some(&Value::String(ref v))) =
+                    if let (Some(Value::String(n)), Some(Value::String(v))) =
                         (table.get("name"), table.get("version"))
                     {
                         (n.to_string(), v.to_string())
@@ -122,6 +128,7 @@ impl Project {
     fn parse_lock_file(
         &self,
         lock_path: PathBuf,
+        finder: &ManifestFinder,
         root_deps: &[DeclaredDep],
         name: &str,
         ver: &str,
@@ -131,12 +138,30 @@ impl Project {
         let mut dg = DepGraph::new(self.cfg.clone());
 
         if let Some(root) = lock_toml.get("root") {
-            parse_package(&mut dg, root, root_deps, name, ver);
+            parse_package(&mut dg, root, finder, root_deps, name, ver);
         }
 
-        if let Some(&Value::Array(ref packages)) = lock_toml.get("package") {
+        if let Some(Value::Array(packages)) = lock_toml.get("package") {
             for pkg in packages {
-                parse_package(&mut dg, pkg, root_deps, name, ver);
+                parse_package(&mut dg, pkg, finder, root_deps, name, ver);
+            }
+        }
+
+        // Older lock files keep the checksums in the metadata table, with keys of the form
+        // "checksum <name> <version> (<source>)".
+        if let Some(Value::Table(metadata)) = lock_toml.get("metadata") {
+            for (key, checksum) in metadata.iter() {
+                let key = key.split(' ').collect::<Vec<_>>();
+                if key.len() < 3 || key[0] != "checksum" {
+                    continue;
+                }
+                match (dg.find(key[1], key[2]), checksum.as_str()) {
+                    // Packages without a checksum are recorded as "<none>".
+                    (Some(id), Some(checksum)) if !checksum.starts_with('<') => {
+                        dg.nodes[id].checksum = Some(checksum.to_owned());
+                    }
+                    _ => (),
+                }
             }
         }
 
@@ -147,6 +172,7 @@ impl Project {
 fn parse_package(
     dg: &mut DepGraph,
     pkg: &Value,
+    finder: &ManifestFinder,
     root_deps: &[DeclaredDep],
     root_name: &str,
     root_version: &str,
@@ -169,6 +195,7 @@ fn parse_package(
              valid string",
         )
         .to_owned();
+    let source = pkg.get("source").and_then(Value::as_str);
 
     // If --filter was specified, keep only packages that were indicated.
     let filter = dg.cfg.filter.clone();
@@ -178,9 +205,24 @@ fn parse_package(
         }
     }
 
-    let id = dg.find_or_add(&*name, &*ver);
+    let id = dg.find_or_add(&name, &ver);
+    if dg.nodes[id].manifest.is_none() {
+        dg.nodes[id].source = source.map(|s| s.to_owned());
+        dg.nodes[id].checksum = pkg
+            .get("checksum")
+            .and_then(Value::as_str)
+            .map(|s| s.to_owned());
+        dg.nodes[id].is_workspace_member =
+            source.is_none() && finder.is_workspace_member(&name, &ver);
+        let manifest = finder.find(&name, &ver, source);
+        if let Some(manifest) = &manifest {
+            dg.nodes[id].license = manifest.license.clone();
+            dg.nodes[id].license_file = manifest.license_file.clone();
+        }
+        dg.nodes[id].manifest = manifest;
+    }
 
-    if let Some(&Value::Array(ref deps)) = pkg.get("dependencies") {
+    if let Some(Value::Array(deps)) = pkg.get("dependencies") {
         for dep in deps {
             let dep_vec = dep.as_str().unwrap_or("").split(' ').collect::<Vec<_>>();
             let dep_name = dep_vec[0].to_owned();
@@ -200,7 +242,116 @@ fn parse_package(
                 continue;
             }
 
-            dg.add_child(id, &*dep_name, dep_ver);
+            if let Some(manifest) = &dg.nodes[id].manifest {
+                if (name != root_name || ver != root_version) && manifest.is_dev_only(&dep_name) {
+                    // Cargo ignores the dev-dependencies of packages other than the root.
+                    continue;
+                }
+            }
+
+            dg.add_child(id, &dep_name, dep_ver);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::license::{self, LicensePolicy};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn dev_deps_are_only_kept_for_the_root() {
+        let dir = env::temp_dir().join(format!("cargo-deps-project-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"root\"\nversion = \"1.0.0\"\n\n\
+             [dependencies]\na = { path = \"a\" }\n\n[dev-dependencies]\nt = \"1\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("a").join("Cargo.toml"),
+            "[package]\nname = \"a\"\nversion = \"1.0.0\"\n\n\
+             [dependencies]\ne = \"1\"\n\n[dev-dependencies]\nd = \"1\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("Cargo.lock"),
+            "[[package]]\nname = \"root\"\nversion = \"1.0.0\"\n\
+             dependencies = [\"a 1.0.0\", \"t 1.0.0\"]\n\n\
+             [[package]]\nname = \"a\"\nversion = \"1.0.0\"\n\
+             dependencies = [\"d 1.0.0\", \"e 1.0.0\"]\n\n\
+             [[package]]\nname = \"d\"\nversion = \"1.0.0\"\n\n\
+             [[package]]\nname = \"e\"\nversion = \"1.0.0\"\n\n\
+             [[package]]\nname = \"t\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+
+        let project = Project::with_config(Config::from_args(&["--dev-deps"])).unwrap();
+        let result = project.graph(dir.join("Cargo.toml"), dir.join("Cargo.lock"));
+        fs::remove_dir_all(&dir).unwrap();
+        let (dg, _) = result.unwrap();
+
+        let edges = dg
+            .edges
+            .iter()
+            .map(|ed| (&*dg.nodes[ed.0].name, &*dg.nodes[ed.1].name))
+            .collect::<Vec<_>>();
+        assert_eq!(edges, [("root", "a"), ("root", "t"), ("a", "e")]);
+        assert!(dg.nodes[dg.find("t", "1.0.0").unwrap()].is_dev);
+    }
+
+    #[test]
+    fn path_deps_outside_of_the_workspace_are_license_checked() {
+        let dir = env::temp_dir().join(format!("cargo-deps-members-{}", std::process::id()));
+        let ws = dir.join("ws");
+        fs::create_dir_all(ws.join("member")).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        fs::write(
+            ws.join("Cargo.toml"),
+            "[package]\nname = \"root\"\nversion = \"1.0.0\"\nlicense = \"GPL-3.0-only\"\n\n\
+             [dependencies]\nmember = { path = \"member\" }\n\
+             outside = { path = \"../outside\" }\n\n[workspace]\nmembers = [\"member\"]\n",
+        )
+        .unwrap();
+        for (path, name) in [
+            (ws.join("member"), "member"),
+            (dir.join("outside"), "outside"),
+        ] {
+            fs::write(
+                path.join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{}\"\nversion = \"1.0.0\"\nlicense = \"GPL-3.0-only\"\n",
+                    name
+                ),
+            )
+            .unwrap();
+        }
+        fs::write(
+            ws.join("Cargo.lock"),
+            "[[package]]\nname = \"root\"\nversion = \"1.0.0\"\n\
+             dependencies = [\"member 1.0.0\", \"outside 1.0.0\"]\n\n\
+             [[package]]\nname = \"member\"\nversion = \"1.0.0\"\n\n\
+             [[package]]\nname = \"outside\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+
+        let project = Project::with_config(Config::from_args(&[])).unwrap();
+        let result = project.graph(ws.join("Cargo.toml"), ws.join("Cargo.lock"));
+        fs::remove_dir_all(&dir).unwrap();
+        let (dg, _) = result.unwrap();
+
+        let policy = LicensePolicy {
+            allow: vec![],
+            deny: vec!["GPL-3.0-only".into()],
+            exceptions: vec![],
+        };
+        let mut output = vec![];
+        assert_eq!(license::check(&dg, &mut output, &policy).unwrap(), 1);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("outside v1.0.0: "), "{}", output);
+        assert!(output.ends_with("    root -> outside\n"), "{}", output);
+    }
+}
@@ -0,0 +1,496 @@
+use crate::dep::{DepKind, ResolvedDep};
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use crate::license::{self, LicenseExpr, LicenseFamily};
+use crate::project::DeclaredDepsMap;
+use crate::util::{self, json_escape};
+use std::collections::{BTreeSet, HashSet};
+use std::io::Write;
+
+const CRATES_IO: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+/// The information of an SPDX document that is shared by the tag-value and JSON formats.
+struct Document {
+    name: String,
+    namespace: String,
+    created: String,
+    creator: String,
+    /// The SPDX identifier of each node, indexed by node.
+    ids: Vec<String>,
+    /// The declared license of each node, indexed by node.
+    licenses: Vec<String>,
+    /// The `LicenseRef-` identifiers used by the declared licenses, with the original identifier.
+    license_refs: BTreeSet<(String, String)>,
+    /// (element, relationship, related element) triples.
+    relationships: Vec<(String, &'static str, String)>,
+}
+
+impl Document {
+    fn new(dg: &DepGraph, root_deps_map: &DeclaredDepsMap) -> Self {
+        let root = &dg.nodes[0];
+        let created = util::utc_timestamp();
+
+        let mut ids = vec![];
+        let mut used = HashSet::new();
+        for (i, dep) in dg.nodes.iter().enumerate() {
+            let mut id = sanitize(&format!("SPDXRef-Package-{}-{}", dep.name, dep.ver));
+            // Sanitizing can make identifiers collide, e.g. for `foo_bar` and `foo-bar`.
+            if !used.insert(id.clone()) {
+                id = format!("{}-{}", id, i);
+                used.insert(id.clone());
+            }
+            ids.push(id);
+        }
+
+        let mut license_refs = BTreeSet::new();
+        let licenses = dg
+            .nodes
+            .iter()
+            .map(|dep| match dep.license_expr() {
+                Some(expr) => spdx_license(&expr, &mut license_refs).to_string(),
+                None => "NOASSERTION".to_owned(),
+            })
+            .collect();
+
+        let mut relationships = vec![("SPDXRef-DOCUMENT".to_owned(), "DESCRIBES", ids[0].clone())];
+        for ed in &dg.edges {
+            // The relationships other than DEPENDS_ON point from the dependency to its dependent.
+            let (parent, child) = (ids[ed.0].clone(), ids[ed.1].clone());
+            relationships.push(match ed.kind(dg, root_deps_map) {
+                DepKind::Build => (child, "BUILD_DEPENDENCY_OF", parent),
+                DepKind::Dev => (child, "DEV_DEPENDENCY_OF", parent),
+                DepKind::Optional => (child, "OPTIONAL_DEPENDENCY_OF", parent),
+                DepKind::Regular | DepKind::Unknown => (parent, "DEPENDS_ON", child),
+            });
+        }
+
+        Document {
+            name: format!("{}-{}", root.name, root.ver),
+            namespace: format!(
+                "https://spdx.org/spdxdocs/{}-{}-{}",
+                root.name,
+                root.ver,
+                created.replace(':', "")
+            ),
+            created,
+            creator: format!("Tool: cargo-deps-{}", env!("CARGO_PKG_VERSION")),
+            ids,
+            licenses,
+            license_refs,
+            relationships,
+        }
+    }
+}
+
+fn download_location(dep: &ResolvedDep) -> String {
+    match &dep.source {
+        Some(source) if source == CRATES_IO => format!(
+            "https://crates.io/api/v1/crates/{}/{}/download",
+            dep.name, dep.ver
+        ),
+        _ => "NOASSERTION".to_owned(),
+    }
+}
+
+/// Keeps the license identifiers known to be SPDX ones and replaces the others with a
+/// `LicenseRef-`, which is recorded in `license_refs` to be declared in the document.
+fn spdx_license(expr: &LicenseExpr, license_refs: &mut BTreeSet<(String, String)>) -> LicenseExpr {
+    match expr {
+        LicenseExpr::License { id, exception } => {
+            let known = license::identifier_family(id) != LicenseFamily::Unknown;
+            if known || id.starts_with("LicenseRef-") {
+                return expr.clone();
+            }
+            let license_ref = format!("LicenseRef-{}", sanitize(id));
+            license_refs.insert((license_ref.clone(), id.clone()));
+            LicenseExpr::License {
+                id: license_ref,
+                exception: exception.clone(),
+            }
+        }
+        LicenseExpr::And(exprs) => LicenseExpr::And(
+            exprs
+                .iter()
+                .map(|e| spdx_license(e, license_refs))
+                .collect(),
+        ),
+        LicenseExpr::Or(exprs) => LicenseExpr::Or(
+            exprs
+                .iter()
+                .map(|e| spdx_license(e, license_refs))
+                .collect(),
+        ),
+    }
+}
+
+/// Replaces the characters not allowed in SPDX identifiers with `-`.
+fn sanitize(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Writes an SPDX 2.3 software bill of materials in the tag-value format.
+pub fn render_tag_value<W: Write>(
+    dg: &DepGraph,
+    output: &mut W,
+    root_deps_map: &DeclaredDepsMap,
+) -> CliResult<()> {
+    let doc = Document::new(dg, root_deps_map);
+
+    writeln!(output, "SPDXVersion: SPDX-2.3")?;
+    writeln!(output, "DataLicense: CC0-1.0")?;
+    writeln!(output, "SPDXID: SPDXRef-DOCUMENT")?;
+    writeln!(output, "DocumentName: {}", doc.name)?;
+    writeln!(output, "DocumentNamespace: {}", doc.namespace)?;
+    writeln!(output, "Creator: {}", doc.creator)?;
+    writeln!(output, "Created: {}", doc.created)?;
+
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        writeln!(output)?;
+        writeln!(output, "PackageName: {}", dep.name)?;
+        writeln!(output, "SPDXID: {}", doc.ids[i])?;
+        writeln!(output, "PackageVersion: {}", dep.ver)?;
+        writeln!(
+            output,
+            "PackageDownloadLocation: {}",
+            download_location(dep)
+        )?;
+        writeln!(output, "FilesAnalyzed: false")?;
+        if let Some(checksum) = &dep.checksum {
+            writeln!(output, "PackageChecksum: SHA256: {}", checksum)?;
+        }
+        writeln!(output, "PackageLicenseConcluded: NOASSERTION")?;
+        writeln!(output, "PackageLicenseDeclared: {}", doc.licenses[i])?;
+        writeln!(output, "PackageCopyrightText: NOASSERTION")?;
+        writeln!(output, "ExternalRef: PACKAGE-MANAGER purl {}", dep.purl())?;
+    }
+
+    for (license_ref, id) in &doc.license_refs {
+        writeln!(output)?;
+        writeln!(output, "LicenseID: {}", license_ref)?;
+        writeln!(output, "ExtractedText: <text>{}</text>", id)?;
+        writeln!(output, "LicenseName: {}", id)?;
+    }
+
+    writeln!(output)?;
+    for (element, relationship, related) in &doc.relationships {
+        writeln!(
+            output,
+            "Relationship: {} {} {}",
+            element, relationship, related
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes an SPDX 2.3 software bill of materials in the JSON format.
+pub fn render_json<W: Write>(
+    dg: &DepGraph,
+    output: &mut W,
+    root_deps_map: &DeclaredDepsMap,
+) -> CliResult<()> {
+    let doc = Document::new(dg, root_deps_map);
+
+    writeln!(output, "{{")?;
+    writeln!(output, "  \"spdxVersion\": \"SPDX-2.3\",")?;
+    writeln!(output, "  \"dataLicense\": \"CC0-1.0\",")?;
+    writeln!(output, "  \"SPDXID\": \"SPDXRef-DOCUMENT\",")?;
+    writeln!(output, "  \"name\": \"{}\",", json_escape(&doc.name))?;
+    writeln!(
+        output,
+        "  \"documentNamespace\": \"{}\",",
+        json_escape(&doc.namespace)
+    )?;
+    writeln!(output, "  \"creationInfo\": {{")?;
+    writeln!(output, "    \"created\": \"{}\",", doc.created)?;
+    writeln!(
+        output,
+        "    \"creators\": [\"{}\"]",
+        json_escape(&doc.creator)
+    )?;
+    writeln!(output, "  }},")?;
+
+    writeln!(output, "  \"packages\": [")?;
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        writeln!(output, "    {{")?;
+        writeln!(output, "      \"name\": \"{}\",", json_escape(&dep.name))?;
+        writeln!(output, "      \"SPDXID\": \"{}\",", doc.ids[i])?;
+        writeln!(
+            output,
+            "      \"versionInfo\": \"{}\",",
+            json_escape(&dep.ver)
+        )?;
+        writeln!(
+            output,
+            "      \"downloadLocation\": \"{}\",",
+            json_escape(&download_location(dep))
+        )?;
+        writeln!(output, "      \"filesAnalyzed\": false,")?;
+        if let Some(checksum) = &dep.checksum {
+            writeln!(
+                output,
+                "      \"checksums\": [{{ \"algorithm\": \"SHA256\", \"checksumValue\": \"{}\" }}],",
+                json_escape(checksum)
+            )?;
+        }
+        writeln!(output, "      \"licenseConcluded\": \"NOASSERTION\",")?;
+        writeln!(
+            output,
+            "      \"licenseDeclared\": \"{}\",",
+            json_escape(&doc.licenses[i])
+        )?;
+        writeln!(output, "      \"copyrightText\": \"NOASSERTION\",")?;
+        writeln!(
+            output,
+            "      \"externalRefs\": [{{ \"referenceCategory\": \"PACKAGE-MANAGER\", \
+             \"referenceType\": \"purl\", \"referenceLocator\": \"{}\" }}]",
+            json_escape(&dep.purl())
+        )?;
+        writeln!(
+            output,
+            "    }}{}",
+            if i + 1 < dg.nodes.len() { "," } else { "" }
+        )?;
+    }
+    writeln!(output, "  ],")?;
+
+    if !doc.license_refs.is_empty() {
+        writeln!(output, "  \"hasExtractedLicensingInfos\": [")?;
+        for (i, (license_ref, id)) in doc.license_refs.iter().enumerate() {
+            writeln!(
+                output,
+                "    {{ \"licenseId\": \"{}\", \"extractedText\": \"{}\", \"name\": \"{}\" }}{}",
+                license_ref,
+                json_escape(id),
+                json_escape(id),
+                if i + 1 < doc.license_refs.len() {
+                    ","
+                } else {
+                    ""
+                }
+            )?;
+        }
+        writeln!(output, "  ],")?;
+    }
+
+    writeln!(output, "  \"relationships\": [")?;
+    for (i, (element, relationship, related)) in doc.relationships.iter().enumerate() {
+        writeln!(
+            output,
+            "    {{ \"spdxElementId\": \"{}\", \"relationshipType\": \"{}\", \
+             \"relatedSpdxElement\": \"{}\" }}{}",
+            element,
+            relationship,
+            related,
+            if i + 1 < doc.relationships.len() {
+                ","
+            } else {
+                ""
+            }
+        )?;
+    }
+    writeln!(output, "  ]")?;
+    writeln!(output, "}}")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dep::DepKind::{Build, Dev, Optional, Regular};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::env;
+
+    /// A root with a regular, a build, a dev and an optional dependency. The regular one depends
+    /// on two crates whose SPDX identifiers collide once sanitized.
+    fn graph() -> (DepGraph, DeclaredDepsMap) {
+        let mut dg = DepGraph::new(Config::from_args(&[]));
+        let root = dg.find_or_add("root", "1.0.0");
+        let regular = dg.add_child(root, "rand", "0.6.5");
+        dg.add_child(root, "cc", "1.0.0");
+        dg.add_child(root, "criterion", "0.3.0");
+        dg.add_child(root, "serde", "1.0.100");
+        dg.add_child(regular, "foo_bar", "1.0.0");
+        dg.add_child(regular, "foo-bar", "1.0.0");
+        dg.nodes[regular].source = Some(CRATES_IO.into());
+        dg.nodes[regular].checksum = Some("ab12".into());
+        dg.nodes[regular].license = Some("MIT OR Custom_License".into());
+
+        let mut declared = HashMap::new();
+        for (name, kind) in [
+            ("rand", Regular),
+            ("cc", Build),
+            ("criterion", Dev),
+            ("serde", Optional),
+        ] {
+            declared.insert(name.to_string(), vec![kind]);
+        }
+        dg.set_resolved_kind(&declared);
+        (dg, declared)
+    }
+
+    #[test]
+    fn tag_value() {
+        env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+        let (dg, declared) = graph();
+        let mut output = vec![];
+        render_tag_value(&dg, &mut output, &declared).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        for expected in [
+            "DocumentName: root-1.0.0\n\
+             DocumentNamespace: https://spdx.org/spdxdocs/root-1.0.0-2023-11-14T221320Z\n",
+            "Created: 2023-11-14T22:13:20Z\n",
+            "PackageName: rand\n\
+             SPDXID: SPDXRef-Package-rand-0.6.5\n\
+             PackageVersion: 0.6.5\n\
+             PackageDownloadLocation: https://crates.io/api/v1/crates/rand/0.6.5/download\n\
+             FilesAnalyzed: false\n\
+             PackageChecksum: SHA256: ab12\n\
+             PackageLicenseConcluded: NOASSERTION\n\
+             PackageLicenseDeclared: MIT OR LicenseRef-Custom-License\n",
+            "PackageName: cc\n\
+             SPDXID: SPDXRef-Package-cc-1.0.0\n\
+             PackageVersion: 1.0.0\n\
+             PackageDownloadLocation: NOASSERTION\n\
+             FilesAnalyzed: false\n\
+             PackageLicenseConcluded: NOASSERTION\n\
+             PackageLicenseDeclared: NOASSERTION\n",
+            "PackageName: foo_bar\nSPDXID: SPDXRef-Package-foo-bar-1.0.0\n",
+            "PackageName: foo-bar\nSPDXID: SPDXRef-Package-foo-bar-1.0.0-6\n",
+            "LicenseID: LicenseRef-Custom-License\n\
+             ExtractedText: <text>Custom_License</text>\n\
+             LicenseName: Custom_License\n",
+        ] {
+            assert!(
+                output.contains(expected),
+                "{}\nnot in:\n{}",
+                expected,
+                output
+            );
+        }
+
+        let relationships = output
+            .lines()
+            .filter(|line| line.starts_with("Relationship: "))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            relationships,
+            [
+                "Relationship: SPDXRef-DOCUMENT DESCRIBES SPDXRef-Package-root-1.0.0",
+                "Relationship: SPDXRef-Package-root-1.0.0 DEPENDS_ON SPDXRef-Package-rand-0.6.5",
+                "Relationship: SPDXRef-Package-cc-1.0.0 BUILD_DEPENDENCY_OF SPDXRef-Package-root-1.0.0",
+                "Relationship: SPDXRef-Package-criterion-0.3.0 DEV_DEPENDENCY_OF SPDXRef-Package-root-1.0.0",
+                "Relationship: SPDXRef-Package-serde-1.0.100 OPTIONAL_DEPENDENCY_OF SPDXRef-Package-root-1.0.0",
+                "Relationship: SPDXRef-Package-rand-0.6.5 DEPENDS_ON SPDXRef-Package-foo-bar-1.0.0",
+                "Relationship: SPDXRef-Package-rand-0.6.5 DEPENDS_ON SPDXRef-Package-foo-bar-1.0.0-6",
+            ]
+        );
+    }
+
+    #[test]
+    fn json() {
+        env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+        let (dg, declared) = graph();
+        let mut output = vec![];
+        render_json(&dg, &mut output, &declared).unwrap();
+        let doc: Value = serde_json::from_slice(&output).unwrap();
+
+        assert_eq!(doc["creationInfo"]["created"], "2023-11-14T22:13:20Z");
+        assert_eq!(
+            doc["documentNamespace"],
+            "https://spdx.org/spdxdocs/root-1.0.0-2023-11-14T221320Z"
+        );
+
+        let packages = doc["packages"].as_array().unwrap();
+        let ids = packages
+            .iter()
+            .map(|p| p["SPDXID"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            [
+                "SPDXRef-Package-root-1.0.0",
+                "SPDXRef-Package-rand-0.6.5",
+                "SPDXRef-Package-cc-1.0.0",
+                "SPDXRef-Package-criterion-0.3.0",
+                "SPDXRef-Package-serde-1.0.100",
+                "SPDXRef-Package-foo-bar-1.0.0",
+                "SPDXRef-Package-foo-bar-1.0.0-6",
+            ]
+        );
+        assert_eq!(
+            packages[1]["checksums"],
+            json!([{ "algorithm": "SHA256", "checksumValue": "ab12" }])
+        );
+        assert_eq!(
+            packages[1]["licenseDeclared"],
+            "MIT OR LicenseRef-Custom-License"
+        );
+        assert!(packages[2].get("checksums").is_none());
+        assert_eq!(packages[2]["licenseDeclared"], "NOASSERTION");
+        assert_eq!(
+            doc["hasExtractedLicensingInfos"],
+            json!([{
+                "licenseId": "LicenseRef-Custom-License",
+                "extractedText": "Custom_License",
+                "name": "Custom_License",
+            }])
+        );
+
+        let relationships = doc["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["spdxElementId"].as_str().unwrap(),
+                    r["relationshipType"].as_str().unwrap(),
+                    r["relatedSpdxElement"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            relationships[..5],
+            [
+                (
+                    "SPDXRef-DOCUMENT",
+                    "DESCRIBES",
+                    "SPDXRef-Package-root-1.0.0"
+                ),
+                (
+                    "SPDXRef-Package-root-1.0.0",
+                    "DEPENDS_ON",
+                    "SPDXRef-Package-rand-0.6.5"
+                ),
+                (
+                    "SPDXRef-Package-cc-1.0.0",
+                    "BUILD_DEPENDENCY_OF",
+                    "SPDXRef-Package-root-1.0.0"
+                ),
+                (
+                    "SPDXRef-Package-criterion-0.3.0",
+                    "DEV_DEPENDENCY_OF",
+                    "SPDXRef-Package-root-1.0.0"
+                ),
+                (
+                    "SPDXRef-Package-serde-1.0.100",
+                    "OPTIONAL_DEPENDENCY_OF",
+                    "SPDXRef-Package-root-1.0.0"
+                ),
+            ]
+        );
+        assert_eq!(relationships.len(), 7);
+    }
+}
@@ -0,0 +1,439 @@
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use crate::layout::{self, Rect};
+use crate::legend::{Entry, Legend};
+use crate::project::DeclaredDepsMap;
+use crate::theme::Attrs;
+use crate::util::xml_escape;
+use std::io::Write;
+
+const FONT_SIZE: f64 = 14.0;
+const NODE_HEIGHT: f64 = 36.0;
+const LEGEND_MARGIN: f64 = 20.0;
+const LEGEND_ROW: f64 = 30.0;
+const LEGEND_PADDING: f64 = 12.0;
+const SWATCH_WIDTH: f64 = 48.0;
+const SWATCH_HEIGHT: f64 = 22.0;
+
+/// Writes the graph as an SVG image, laid out with the built-in layered layout, so that no
+/// external tool is needed. Nodes and edges are styled with the DOT attributes of the theme.
+pub fn render<W: Write>(
+    dg: &DepGraph,
+    output: &mut W,
+    root_deps_map: &DeclaredDepsMap,
+) -> CliResult<()> {
+    let theme = &dg.cfg.theme;
+    let text_width = |text: &str| text.chars().count() as f64 * FONT_SIZE * 0.55;
+    let labels = dg
+        .nodes
+        .iter()
+        .map(|dep| dep.display_name(&dg.cfg))
+        .collect::<Vec<_>>();
+    let sizes = labels
+        .iter()
+        .map(|label| {
+            let width = text_width(label) + 30.0;
+            (width.max(54.0), NODE_HEIGHT)
+        })
+        .collect::<Vec<_>>();
+    let edges = dg.edges.iter().map(|ed| (ed.0, ed.1)).collect::<Vec<_>>();
+    let in_cluster = dg
+        .nodes
+        .iter()
+        .map(|dep| {
+            dg.cfg
+                .subgraph
+                .as_ref()
+                .is_some_and(|sub_deps| sub_deps.contains(&dep.name))
+        })
+        .collect::<Vec<_>>();
+    let layout = layout::layout(&sizes, &edges, &in_cluster);
+
+    let node_attrs = dg
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, dep)| {
+            let mut attrs = theme.node.clone();
+            attrs.merge(&dep.attrs(&dg.cfg, i));
+            attrs
+        })
+        .collect::<Vec<_>>();
+    let edge_attrs = dg
+        .edges
+        .iter()
+        .map(|ed| {
+            let mut attrs = theme.edge.clone();
+            attrs.merge(&ed.attrs(dg, root_deps_map));
+            attrs
+        })
+        .collect::<Vec<_>>();
+
+    // The legend is drawn to the right of the graph, with the node styles above the edge styles.
+    let legend = if dg.cfg.legend {
+        let legend = Legend::new(dg, root_deps_map);
+        let mut entries = legend.node_entries(theme);
+        for entry in &mut entries {
+            let mut attrs = theme.node.clone();
+            attrs.merge(&entry.attrs);
+            entry.attrs = attrs;
+        }
+        let mut edge_entries = legend.edge_entries(theme);
+        for entry in &mut edge_entries {
+            let mut attrs = theme.edge.clone();
+            attrs.merge(&entry.attrs);
+            entry.attrs = attrs;
+        }
+        Some((entries, edge_entries))
+    } else {
+        None
+    };
+    let legend_box = legend.as_ref().map(|(nodes, edges)| {
+        let label_width = nodes
+            .iter()
+            .chain(edges)
+            .map(|entry| text_width(&entry.label))
+            .fold(text_width("Legend"), f64::max);
+        let width = LEGEND_PADDING * 3.0 + SWATCH_WIDTH + label_width;
+        let height = LEGEND_PADDING * 2.0 + LEGEND_ROW * (1 + nodes.len() + edges.len()) as f64;
+        Rect {
+            x: layout.width + width / 2.0,
+            y: LEGEND_MARGIN + height / 2.0,
+            width,
+            height,
+        }
+    });
+    let (width, height) = match legend_box {
+        Some(b) => (
+            b.x + b.width / 2.0 + LEGEND_MARGIN,
+            layout.height.max(b.y + b.height / 2.0 + LEGEND_MARGIN),
+        ),
+        None => (layout.width, layout.height),
+    };
+
+    writeln!(
+        output,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.0}\" height=\"{1:.0}\" \
+         viewBox=\"0 0 {0:.0} {1:.0}\">",
+        width, height
+    )?;
+    let font = theme.node.get("fontname").unwrap_or("Times,serif");
+    writeln!(
+        output,
+        "<style>text {{ font-family: {}; font-size: {}px; }}</style>",
+        xml_escape(font),
+        FONT_SIZE
+    )?;
+
+    // One arrowhead marker per edge color.
+    let legend_edge_attrs = legend
+        .iter()
+        .flat_map(|(_, edges)| edges.iter().map(|entry| &entry.attrs));
+    let mut colors = edge_attrs
+        .iter()
+        .chain(legend_edge_attrs)
+        .map(|attrs| color(attrs.get("color"), "black"))
+        .collect::<Vec<_>>();
+    colors.sort();
+    colors.dedup();
+    writeln!(output, "<defs>")?;
+    for (i, c) in colors.iter().enumerate() {
+        writeln!(
+            output,
+            "\t<marker id=\"arrow{}\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+             markerWidth=\"8\" markerHeight=\"8\" orient=\"auto\">\
+             <path d=\"M0,0L10,5L0,10z\" fill=\"{}\"/></marker>",
+            i, c
+        )?;
+    }
+    writeln!(output, "</defs>")?;
+
+    let background = theme.graph.get("bgcolor");
+    if let Some(background) = background {
+        writeln!(
+            output,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+            color(Some(background), "white")
+        )?;
+    }
+
+    if let Some(cluster) = layout.cluster {
+        let attrs = &theme.subgraph;
+        writeln!(output, "<g id=\"subgraph\">")?;
+        writeln!(
+            output,
+            "\t{} fill=\"{}\" stroke=\"{}\"{}/>",
+            shape("box", cluster),
+            color(attrs.get("bgcolor"), "none"),
+            color(attrs.get("color"), "black"),
+            stroke_style(attrs)
+        )?;
+        if let Some(name) = &dg.cfg.subgraph_name {
+            writeln!(
+                output,
+                "\t<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" \
+                 dominant-baseline=\"central\" fill=\"{}\">{}</text>",
+                cluster.x,
+                cluster.y - cluster.height / 2.0 + 16.0,
+                color(attrs.get("fontcolor"), "black"),
+                xml_escape(name)
+            )?;
+        }
+        writeln!(output, "</g>")?;
+    }
+
+    writeln!(output, "<g id=\"edges\">")?;
+    for (points, attrs) in layout.edges.iter().zip(&edge_attrs) {
+        let stroke = color(attrs.get("color"), "black");
+        let marker = colors.iter().position(|c| *c == stroke).unwrap();
+        writeln!(
+            output,
+            "\t<path d=\"{}\" fill=\"none\" stroke=\"{}\"{} marker-end=\"url(#arrow{})\"/>",
+            path(points),
+            stroke,
+            stroke_style(attrs),
+            marker
+        )?;
+    }
+    writeln!(output, "</g>")?;
+
+    writeln!(output, "<g id=\"nodes\">")?;
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        let attrs = &node_attrs[i];
+        let rect = layout.nodes[i];
+        let (fill, stroke) = node_colors(attrs, background);
+
+        writeln!(output, "\t<g>")?;
+        writeln!(
+            output,
+            "\t\t<title>{} v{}</title>",
+            xml_escape(&dep.name),
+            xml_escape(&dep.ver)
+        )?;
+        writeln!(
+            output,
+            "\t\t{} fill=\"{}\" stroke=\"{}\"{}/>",
+            shape(attrs.get("shape").unwrap_or("ellipse"), rect),
+            fill,
+            stroke,
+            stroke_style(attrs)
+        )?;
+        writeln!(
+            output,
+            "\t\t<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" \
+             dominant-baseline=\"central\" fill=\"{}\">{}</text>",
+            rect.x,
+            rect.y,
+            color(attrs.get("fontcolor"), "black"),
+            xml_escape(&labels[i])
+        )?;
+        writeln!(output, "\t</g>")?;
+    }
+    writeln!(output, "</g>")?;
+
+    if let (Some((nodes, edges)), Some(b)) = (&legend, legend_box) {
+        let label_color = color(theme.graph.get("fontcolor"), "black");
+        render_legend(output, b, nodes, edges, background, &label_color, &colors)?;
+    }
+    writeln!(output, "</svg>")?;
+
+    Ok(())
+}
+
+/// Draws the legend in the box `b`: a row with a sample node or edge and its description for each
+/// entry.
+fn render_legend<W: Write>(
+    output: &mut W,
+    b: Rect,
+    nodes: &[Entry],
+    edges: &[Entry],
+    background: Option<&str>,
+    label_color: &str,
+    colors: &[String],
+) -> CliResult<()> {
+    writeln!(output, "<g id=\"legend\">")?;
+    writeln!(
+        output,
+        "\t{} fill=\"none\" stroke=\"{}\"/>",
+        shape("box", b),
+        color(Some("gray50"), "black")
+    )?;
+    let top = b.y - b.height / 2.0;
+    let row_y = |row: usize| top + LEGEND_PADDING + LEGEND_ROW * (row as f64 + 0.5);
+    let swatch_x = b.x - b.width / 2.0 + LEGEND_PADDING;
+    let text_x = swatch_x + SWATCH_WIDTH + LEGEND_PADDING;
+    writeln!(
+        output,
+        "\t<text x=\"{:.1}\" y=\"{:.1}\" dominant-baseline=\"central\" fill=\"{}\">Legend</text>",
+        swatch_x,
+        row_y(0),
+        label_color
+    )?;
+
+    for (i, entry) in nodes.iter().enumerate() {
+        let y = row_y(i + 1);
+        let rect = Rect {
+            x: swatch_x + SWATCH_WIDTH / 2.0,
+            y,
+            width: SWATCH_WIDTH,
+            height: SWATCH_HEIGHT,
+        };
+        let (fill, stroke) = node_colors(&entry.attrs, background);
+        writeln!(
+            output,
+            "\t{} fill=\"{}\" stroke=\"{}\"{}/>",
+            shape(entry.attrs.get("shape").unwrap_or("ellipse"), rect),
+            fill,
+            stroke,
+            stroke_style(&entry.attrs)
+        )?;
+        writeln!(
+            output,
+            "\t<text x=\"{:.1}\" y=\"{:.1}\" dominant-baseline=\"central\" fill=\"{}\">{}</text>",
+            text_x,
+            y,
+            label_color,
+            xml_escape(&entry.label)
+        )?;
+    }
+
+    for (i, entry) in edges.iter().enumerate() {
+        let y = row_y(nodes.len() + i + 1);
+        let stroke = color(entry.attrs.get("color"), "black");
+        let marker = colors.iter().position(|c| *c == stroke).unwrap();
+        writeln!(
+            output,
+            "\t<path d=\"M{:.1},{:.1} H{:.1}\" fill=\"none\" stroke=\"{}\"{} \
+             marker-end=\"url(#arrow{})\"/>",
+            swatch_x,
+            y,
+            swatch_x + SWATCH_WIDTH,
+            stroke,
+            stroke_style(&entry.attrs),
+            marker
+        )?;
+        writeln!(
+            output,
+            "\t<text x=\"{:.1}\" y=\"{:.1}\" dominant-baseline=\"central\" fill=\"{}\">{}</text>",
+            text_x,
+            y,
+            label_color,
+            xml_escape(&entry.label)
+        )?;
+    }
+    writeln!(output, "</g>")?;
+
+    Ok(())
+}
+
+/// The fill and stroke colors of a node with the given DOT attributes.
+fn node_colors(attrs: &Attrs, background: Option<&str>) -> (String, String) {
+    let styles = attrs.get("style").unwrap_or("");
+    let fill = if styles.contains("filled") {
+        color(attrs.get("fillcolor"), "lightgray")
+    } else {
+        color(background, "white")
+    };
+    (fill, color(attrs.get("color"), "black"))
+}
+
+/// Converts a DOT color to an SVG color. The X11 `grayN` scale isn't known to SVG.
+fn color(color: Option<&str>, default: &str) -> String {
+    let color = color.unwrap_or(default).to_lowercase();
+    let level = color
+        .strip_prefix("gray")
+        .or_else(|| color.strip_prefix("grey"))
+        .and_then(|level| level.parse::<f64>().ok());
+
+    match level {
+        Some(level) if level <= 100.0 => {
+            let v = (level * 2.55).round() as u8;
+            format!("#{0:02x}{0:02x}{0:02x}", v)
+        }
+        _ => xml_escape(&color),
+    }
+}
+
+/// The stroke attributes for the `style` and `penwidth` DOT attributes.
+fn stroke_style(attrs: &Attrs) -> String {
+    let styles = attrs.get("style").unwrap_or("");
+    let mut result = String::new();
+
+    let mut width = attrs
+        .get("penwidth")
+        .and_then(|w| w.parse::<f64>().ok())
+        .unwrap_or(1.0);
+    if styles.contains("bold") {
+        width = width.max(2.0);
+    }
+    if width != 1.0 {
+        result.push_str(&format!(" stroke-width=\"{}\"", width));
+    }
+
+    if styles.contains("dashed") {
+        result.push_str(" stroke-dasharray=\"6,4\"");
+    } else if styles.contains("dotted") {
+        result.push_str(" stroke-dasharray=\"2,3\"");
+    }
+
+    result
+}
+
+/// A smooth path through the points of an edge, which leaves and enters nodes vertically.
+fn path(points: &[(f64, f64)]) -> String {
+    let mut d = format!("M{:.1},{:.1}", points[0].0, points[0].1);
+    for pair in points.windows(2) {
+        let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+        let mid = (y1 + y2) / 2.0;
+        d.push_str(&format!(
+            " C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}",
+            x1, mid, x2, mid, x2, y2
+        ));
+    }
+    d
+}
+
+/// The SVG element of a node shape, without its closing `/>`.
+fn shape(shape: &str, r: Rect) -> String {
+    let (hw, hh) = (r.width / 2.0, r.height / 2.0);
+    let polygon = |points: &[(f64, f64)]| {
+        let points = points
+            .iter()
+            .map(|(dx, dy)| format!("{:.1},{:.1}", r.x + dx * hw, r.y + dy * hh))
+            .collect::<Vec<_>>();
+        format!("<polygon points=\"{}\"", points.join(" "))
+    };
+
+    match shape {
+        "box" | "rect" | "rectangle" | "square" => format!(
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"",
+            r.x - hw,
+            r.y - hh,
+            r.width,
+            r.height
+        ),
+        "hexagon" => polygon(&[
+            (-1.0, 0.0),
+            (-0.75, -1.0),
+            (0.75, -1.0),
+            (1.0, 0.0),
+            (0.75, 1.0),
+            (-0.75, 1.0),
+        ]),
+        "diamond" => polygon(&[(-1.0, 0.0), (0.0, -1.0), (1.0, 0.0), (0.0, 1.0)]),
+        "octagon" | "doubleoctagon" => polygon(&[
+            (-1.0, -0.4),
+            (-0.8, -1.0),
+            (0.8, -1.0),
+            (1.0, -0.4),
+            (1.0, 0.4),
+            (0.8, 1.0),
+            (-0.8, 1.0),
+            (-1.0, 0.4),
+        ]),
+        _ => format!(
+            "<ellipse cx=\"{:.1}\" cy=\"{:.1}\" rx=\"{:.1}\" ry=\"{:.1}\"",
+            r.x, r.y, hw, hh
+        ),
+    }
+}
@@ -0,0 +1,338 @@
+use crate::dep::DepKind;
+use crate::error::{CliError, CliResult};
+use crate::util::dot_escape;
+use std::fmt;
+use toml::Value;
+
+/// A list of DOT attributes, written in the order they were first set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Attrs(Vec<(String, String)>);
+
+impl Attrs {
+    pub fn new(attrs: &[(&str, &str)]) -> Self {
+        let mut result = Attrs::default();
+        for (key, value) in attrs {
+            result.set(key, value);
+        }
+        result
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Sets `key` to `value`, keeping its position if it was already set.
+    pub fn set(&mut self, key: &str, value: &str) {
+        match self.0.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.into(),
+            None => self.0.push((key.into(), value.into())),
+        }
+    }
+
+    /// Adds `style` to the comma-separated `style` attribute, e.g. `filled` to `dashed`.
+    pub fn add_style(&mut self, style: &str) {
+        let styles = match self.get("style") {
+            Some(styles) if !styles.split(',').any(|s| s.trim() == style) => {
+                format!("{},{}", styles, style)
+            }
+            Some(styles) => styles.into(),
+            None => style.into(),
+        };
+        self.set("style", &styles);
+    }
+
+    /// Iterates over the attributes, with the values as written in DOT: values that aren't plain
+    /// identifiers or numbers are quoted.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, String)> {
+        self.0.iter().map(|(key, value)| {
+            let is_plain = !value.is_empty()
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+            if is_plain {
+                (key.as_str(), value.clone())
+            } else {
+                (key.as_str(), format!("\"{}\"", dot_escape(value)))
+            }
+        })
+    }
+
+    /// Sets all the attributes of `other`, overriding the existing ones.
+    pub fn merge(&mut self, other: &Attrs) {
+        for (key, value) in &other.0 {
+            self.set(key, value);
+        }
+    }
+
+    pub fn from_toml(value: &Value, name: &str) -> CliResult<Self> {
+        let table = value
+            .as_table()
+            .ok_or_else(|| CliError::Toml(format!("`{}` must be a table", name)))?;
+
+        let mut attrs = Attrs::default();
+        for (key, value) in table {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Integer(_) | Value::Float(_) | Value::Boolean(_) => value.to_string(),
+                _ => {
+                    return Err(CliError::Toml(format!(
+                        "`{}.{}` must be a string, a number or a boolean",
+                        name, key
+                    )))
+                }
+            };
+            attrs.set(key, &value);
+        }
+        Ok(attrs)
+    }
+}
+
+/// Writes the attributes as `key=value` pairs separated by commas.
+impl fmt::Display for Attrs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The attributes of the nodes and edges of one kind of dependency.
+#[derive(Clone, Debug, Default)]
+pub struct KindStyle {
+    pub node: Attrs,
+    pub edge: Attrs,
+}
+
+impl KindStyle {
+    fn new(node: &[(&str, &str)], edge: &[(&str, &str)]) -> Self {
+        KindStyle {
+            node: Attrs::new(node),
+            edge: Attrs::new(edge),
+        }
+    }
+}
+
+/// The styling of the DOT output.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// Default attributes of the graph, nodes and edges, written at the top of the graph.
+    pub graph: Attrs,
+    pub node: Attrs,
+    pub edge: Attrs,
+
+    pub regular: KindStyle,
+    pub build: KindStyle,
+    pub dev: KindStyle,
+    pub optional: KindStyle,
+    pub unknown: KindStyle,
+
+    /// Attributes of the root node, on top of those of its kind.
+    pub root: Attrs,
+    /// Attributes of the subgraph cluster.
+    pub subgraph: Attrs,
+}
+
+impl Theme {
+    pub const NAMES: &'static [&'static str] = &["default", "dark", "colorblind", "monochrome"];
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default_theme()),
+            "dark" => Some(Self::dark()),
+            "colorblind" => Some(Self::colorblind()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// Builds the theme from the `--theme` name and the `[theme]` table of the config file. The
+    /// table can pick a built-in theme to start from with `base` (if `--theme` isn't given), and
+    /// override its attributes with the `graph`, `node`, `edge`, `root` and `subgraph` tables and
+    /// the per-kind `nodes.<kind>` and `edges.<kind>` tables.
+    pub fn from_config(name: Option<&str>, table: Option<&Value>) -> CliResult<Self> {
+        let base = name
+            .or_else(|| table.and_then(|t| t.get("base")).and_then(Value::as_str))
+            .unwrap_or("default");
+        let mut theme = Self::builtin(base).ok_or_else(|| {
+            CliError::Generic(format!(
+                "Unknown theme `{}`, expected one of: {}",
+                base,
+                Self::NAMES.join(", ")
+            ))
+        })?;
+
+        let table = match table {
+            Some(table) => table,
+            None => return Ok(theme),
+        };
+        let overrides = [
+            ("graph", &mut theme.graph),
+            ("node", &mut theme.node),
+            ("edge", &mut theme.edge),
+            ("root", &mut theme.root),
+            ("subgraph", &mut theme.subgraph),
+        ];
+        for (key, attrs) in overrides {
+            if let Some(value) = table.get(key) {
+                attrs.merge(&Attrs::from_toml(value, &format!("theme.{}", key))?);
+            }
+        }
+        for (key, kinds) in [("nodes", true), ("edges", false)] {
+            let kinds_table = match table.get(key) {
+                Some(kinds_table) => kinds_table,
+                None => continue,
+            };
+            for (kind, style) in [
+                ("regular", &mut theme.regular),
+                ("build", &mut theme.build),
+                ("dev", &mut theme.dev),
+                ("optional", &mut theme.optional),
+                ("unknown", &mut theme.unknown),
+            ] {
+                if let Some(value) = kinds_table.get(kind) {
+                    let attrs = Attrs::from_toml(value, &format!("theme.{}.{}", key, kind))?;
+                    if kinds {
+                        style.node.merge(&attrs);
+                    } else {
+                        style.edge.merge(&attrs);
+                    }
+                }
+            }
+        }
+
+        Ok(theme)
+    }
+
+    pub fn kind(&self, kind: DepKind) -> &KindStyle {
+        match kind {
+            DepKind::Regular => &self.regular,
+            DepKind::Build => &self.build,
+            DepKind::Dev => &self.dev,
+            DepKind::Optional => &self.optional,
+            DepKind::Unknown => &self.unknown,
+        }
+    }
+
+    /// Colored outlines and dashed edges for the kinds other than regular.
+    fn default_theme() -> Self {
+        let colored = |color| {
+            KindStyle::new(
+                &[("color", color)],
+                &[("color", color), ("style", "dashed")],
+            )
+        };
+
+        Theme {
+            graph: Attrs::default(),
+            node: Attrs::default(),
+            edge: Attrs::default(),
+            regular: KindStyle::default(),
+            build: colored(DepKind::Build.color()),
+            dev: colored(DepKind::Dev.color()),
+            optional: colored(DepKind::Optional.color()),
+            unknown: colored(DepKind::Unknown.color()),
+            root: Attrs::new(&[("shape", "box")]),
+            subgraph: Attrs::new(&[("color", "brown"), ("style", "dashed")]),
+        }
+    }
+
+    /// Light colors on a dark background.
+    fn dark() -> Self {
+        let colored = |color| {
+            KindStyle::new(
+                &[("color", color), ("fontcolor", color)],
+                &[("color", color), ("style", "dashed")],
+            )
+        };
+
+        Theme {
+            graph: Attrs::new(&[("bgcolor", "gray12"), ("fontcolor", "white")]),
+            node: Attrs::new(&[("color", "white"), ("fontcolor", "white")]),
+            edge: Attrs::new(&[("color", "gray70")]),
+            regular: KindStyle::default(),
+            build: colored("plum"),
+            dev: colored("lightskyblue"),
+            optional: colored("salmon"),
+            unknown: colored("gold"),
+            root: Attrs::new(&[("shape", "box"), ("penwidth", "2")]),
+            subgraph: Attrs::new(&[("color", "burlywood"), ("style", "dashed")]),
+        }
+    }
+
+    /// The Okabe-Ito palette, which can be told apart with the common forms of color blindness.
+    /// Each kind also has its own edge style, so that it doesn't rely on color alone.
+    fn colorblind() -> Self {
+        let styled = |color, style| {
+            KindStyle::new(
+                &[("color", color), ("penwidth", "2")],
+                &[("color", color), ("style", style)],
+            )
+        };
+
+        Theme {
+            graph: Attrs::default(),
+            node: Attrs::default(),
+            edge: Attrs::default(),
+            regular: KindStyle::default(),
+            build: styled("#0072B2", "dashed"),
+            dev: styled("#E69F00", "dotted"),
+            optional: styled("#CC79A7", "tapered"),
+            unknown: styled("#D55E00", "bold"),
+            root: Attrs::new(&[("shape", "box")]),
+            subgraph: Attrs::new(&[("color", "#009E73"), ("style", "dashed")]),
+        }
+    }
+
+    /// Black and white, for printing. The kinds are told apart by line style.
+    fn monochrome() -> Self {
+        let styled = |style| KindStyle::new(&[("style", style)], &[("style", style)]);
+
+        Theme {
+            graph: Attrs::default(),
+            node: Attrs::new(&[("color", "black"), ("fontcolor", "black")]),
+            edge: Attrs::new(&[("color", "black")]),
+            regular: KindStyle::default(),
+            build: styled("dashed"),
+            dev: styled("dotted"),
+            optional: KindStyle::new(
+                &[("style", "dashed"), ("peripheries", "2")],
+                &[("style", "dashed"), ("arrowhead", "empty")],
+            ),
+            unknown: styled("bold"),
+            root: Attrs::new(&[("shape", "box")]),
+            subgraph: Attrs::new(&[("color", "black"), ("style", "dashed")]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_quoted_and_escaped() {
+        let attrs = Attrs::new(&[
+            ("shape", "box"),
+            ("penwidth", "1.5"),
+            ("color", "#009E73"),
+            ("label", ""),
+            ("tooltip", "a \"b\"\\c\nd"),
+        ]);
+        assert_eq!(
+            attrs.to_string(),
+            "shape=box, penwidth=1.5, color=\"#009E73\", label=\"\", \
+             tooltip=\"a \\\"b\\\"\\\\c\\nd\""
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::env;
 use std::fs::{self, File};
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use toml::{self, Value};
 
 pub fn toml_from_file<P: AsRef<Path>>(p: P) -> CliResult<Value> {
@@ -53,7 +54,171 @@ pub fn find_manifest_file(file: &PathBuf) -> CliResult<PathBuf> {
                     pwd.display()
                 )));
             }
-            Some(ref dir) => dir.to_path_buf(),
+            Some(dir) => dir.to_path_buf(),
         };
     }
 }
+
+/// Escapes a string so that it can be embedded in a JSON string literal. `<` and `>` are escaped
+/// as well, so that the result is also safe to embed in an HTML `<script>` tag.
+pub fn json_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '<' => escaped.push_str("\\u003c"),
+            '>' => escaped.push_str("\\u003e"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes a string so that it can be used in XML text and attribute values.
+pub fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes `s` for a quoted DOT string, so that quotes and backslashes are written literally.
+pub fn dot_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns true if `text` matches the glob `pattern`, where `*` matches any sequence of characters
+/// and `?` any single character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // The position after the last `*` and the text position it is currently matched up to.
+    let mut star = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Returns the current time as an ISO 8601 UTC timestamp. `$SOURCE_DATE_EPOCH` overrides the
+/// current time, to allow reproducible outputs.
+pub fn utc_timestamp() -> String {
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+    format_timestamp(secs)
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC timestamp.
+fn format_timestamp(secs: u64) -> String {
+    // Convert days since the epoch to a civil date (Howard Hinnant's algorithm).
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let time = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("serde", "serde"));
+        assert!(!glob_match("serde", "serde_json"));
+
+        assert!(glob_match("serde*", "serde"));
+        assert!(glob_match("serde*", "serde_derive"));
+        assert!(!glob_match("serde*", "ser"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*_sys", "openssl_sys"));
+        assert!(glob_match("*ssl*", "openssl-sys"));
+
+        assert!(glob_match("rand_core@0.?.?", "rand_core@0.4.2"));
+        assert!(!glob_match("rand_core@0.?.?", "rand_core@0.4.20"));
+        assert!(!glob_match("?", ""));
+
+        // The first `*` has to give back what it matched for the rest of the pattern to match.
+        assert!(glob_match("*a*b", "aaab_a_b"));
+        assert!(glob_match("a*b*c", "abcbcbc"));
+        assert!(!glob_match("a*b*c", "abcbcb"));
+        assert!(glob_match("*-*-?", "x-y-z-w"));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(951_868_799), "2000-02-29T23:59:59Z");
+        assert_eq!(format_timestamp(1_709_251_199), "2024-02-29T23:59:59Z");
+        assert_eq!(format_timestamp(1_735_689_599), "2024-12-31T23:59:59Z");
+        assert_eq!(format_timestamp(4_107_542_400), "2100-03-01T00:00:00Z");
+
+        // Other tests set the same value, so that it does not matter which one runs first.
+        env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+        assert_eq!(utc_timestamp(), "2023-11-14T22:13:20Z");
+    }
+}